@@ -8,25 +8,40 @@ use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
 use std::fs;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-// 使用外部 crate (需要在 Cargo.toml 中添加)
+// 使用外部 crate (需要在 Cargo.toml 中添加):
+// serde (derive)、serde_json、toml、uuid (v4、v5、serde)、chrono、log、env_logger、
+// rayon、crossbeam-channel、once_cell、tokio (full)、tokio-util、tokio-stream、
+// walkdir、ignore、globset、notify、regex、ropey、sha2、blake3、xxhash-rust (xxh3)、
+// encoding_rs、chardetng、zip (deflate)、tar、flate2、futures、tokio-tungstenite
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use tokio;
+use sha2::Digest;
 use uuid::Uuid;
 
 /// 应用程序常量
 const APP_NAME: &str = "Chango Editor";
 const APP_VERSION: &str = "0.1.0";
-const MAX_FILE_SIZE: usize = 100 * 1024 * 1024; // 100MB
-const SUPPORTED_EXTENSIONS: &[&str] = &[".rs", ".py", ".js", ".ts", ".go", ".java", ".cpp", ".cs"];
+const MAX_FILE_SIZE: u64 = 100 * 1024 * 1024; // 100MB
+const READ_BUFFER_SIZE: usize = 64 * 1024; // 64KB
+const BINARY_SNIFF_SIZE: usize = 8 * 1024; // 8KB
+const PROJECT_CONFIG_FILE: &str = ".chango.toml";
+const MAX_DEFAULT_WORKERS: usize = 16;
+const SUPPORTED_EXTENSIONS: &[&str] = &[
+    ".rs", ".py", ".js", ".ts", ".go", ".java", ".cpp", ".cs",
+    ".c", ".h", ".rb", ".php", ".swift", ".kt", ".kts",
+];
 
 /// 编程语言枚举
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// 序列化为稳定的小写标识 (见 [`Language::id`])，不依赖变体的名称与顺序。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Language {
     Rust,
     Python,
@@ -36,10 +51,63 @@ pub enum Language {
     Java,
     Cpp,
     CSharp,
+    C,
+    Ruby,
+    Php,
+    Swift,
+    Kotlin,
     Unknown,
 }
 
 impl Language {
+    /// 所有语言变体 (含 Unknown)
+    pub const ALL: &'static [Language] = &[
+        Language::Rust,
+        Language::Python,
+        Language::JavaScript,
+        Language::TypeScript,
+        Language::Go,
+        Language::Java,
+        Language::Cpp,
+        Language::CSharp,
+        Language::C,
+        Language::Ruby,
+        Language::Php,
+        Language::Swift,
+        Language::Kotlin,
+        Language::Unknown,
+    ];
+    
+    /// 稳定的小写标识，用于序列化与配置文件
+    pub fn id(&self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::Python => "python",
+            Language::JavaScript => "javascript",
+            Language::TypeScript => "typescript",
+            Language::Go => "go",
+            Language::Java => "java",
+            Language::Cpp => "cpp",
+            Language::CSharp => "csharp",
+            Language::C => "c",
+            Language::Ruby => "ruby",
+            Language::Php => "php",
+            Language::Swift => "swift",
+            Language::Kotlin => "kotlin",
+            Language::Unknown => "unknown",
+        }
+    }
+    
+    /// 从标识解析语言 (不区分大小写，因此也接受旧的变体名称，如 `"CSharp"`)
+    ///
+    /// 不是标识时按扩展名解析 (如 `"rs"`)，仍无法识别则返回 `Unknown`。
+    pub fn from_id(id: &str) -> Self {
+        Language::ALL.iter()
+            .copied()
+            .find(|language| language.id().eq_ignore_ascii_case(id))
+            .unwrap_or_else(|| Language::from_extension(id))
+    }
+    
     /// 从文件扩展名获取语言类型
     pub fn from_extension(ext: &str) -> Self {
         match ext.to_lowercase().as_str() {
@@ -51,10 +119,66 @@ impl Language {
             "java" => Language::Java,
             "cpp" | "cxx" | "cc" | "c++" => Language::Cpp,
             "cs" => Language::CSharp,
+            "c" | "h" => Language::C,
+            "rb" => Language::Ruby,
+            "php" => Language::Php,
+            "swift" => Language::Swift,
+            "kt" | "kts" => Language::Kotlin,
+            _ => Language::Unknown,
+        }
+    }
+    
+    /// 从文件首行的 shebang 推断语言类型
+    ///
+    /// 支持 `#!/usr/bin/python3` 直接调用与 `#!/usr/bin/env python3` 间接调用，
+    /// 解释器名称上的版本后缀（如 `python3.11`）会被忽略。
+    pub fn from_content(first_line: &str) -> Self {
+        let line = first_line.trim_start_matches('\u{feff}').trim();
+        let command = match line.strip_prefix("#!") {
+            Some(command) => command,
+            None => return Language::Unknown,
+        };
+        
+        let mut parts = command.split_whitespace();
+        let mut interpreter = parts.next().unwrap_or("");
+        
+        // env 间接调用: 跳过选项 (如 -S) 与环境变量赋值，取真正的解释器
+        if interpreter.rsplit('/').next() == Some("env") {
+            interpreter = parts
+                .find(|arg| !arg.starts_with('-') && !arg.contains('='))
+                .unwrap_or("");
+        }
+        
+        let name = interpreter
+            .rsplit('/')
+            .next()
+            .unwrap_or("")
+            .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        
+        match name {
+            "python" | "pypy" => Language::Python,
+            "node" | "nodejs" => Language::JavaScript,
+            "ts-node" => Language::TypeScript,
+            "ruby" => Language::Ruby,
+            "php" => Language::Php,
             _ => Language::Unknown,
         }
     }
     
+    /// 综合扩展名与首行内容检测语言类型，扩展名无法识别时回退到 shebang
+    pub fn detect(path: &Path, first_line: &str) -> Self {
+        let language = path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(Language::from_extension)
+            .unwrap_or(Language::Unknown);
+        
+        if language == Language::Unknown {
+            Language::from_content(first_line)
+        } else {
+            language
+        }
+    }
+    
     /// 获取语言的关键字
     pub fn keywords(&self) -> &'static [&'static str] {
         match self {
@@ -75,9 +199,108 @@ impl Language {
                 "break", "continue", "throw", "new", "this", "super", "extends", "import",
                 "export", "async", "await", "typeof", "instanceof",
             ],
+            // TypeScript 是 JavaScript 的超集: JavaScript 关键字加上类型相关关键字
+            Language::TypeScript => &[
+                "var", "let", "const", "function", "class", "if", "else", "for", "while",
+                "do", "switch", "case", "default", "try", "catch", "finally", "return",
+                "break", "continue", "throw", "new", "this", "super", "extends", "import",
+                "export", "async", "await", "typeof", "instanceof",
+                "interface", "type", "enum", "namespace", "module", "declare", "implements",
+                "abstract", "public", "private", "protected", "readonly", "keyof", "as",
+                "is", "infer", "satisfies",
+            ],
+            Language::Go => &[
+                "break", "case", "chan", "const", "continue", "default", "defer", "else",
+                "fallthrough", "for", "func", "go", "goto", "if", "import", "interface",
+                "map", "package", "range", "return", "select", "struct", "switch", "type",
+                "var",
+            ],
+            Language::Java => &[
+                "abstract", "assert", "boolean", "break", "byte", "case", "catch", "char",
+                "class", "const", "continue", "default", "do", "double", "else", "enum",
+                "extends", "final", "finally", "float", "for", "goto", "if", "implements",
+                "import", "instanceof", "int", "interface", "long", "native", "new",
+                "package", "private", "protected", "public", "return", "short", "static",
+                "strictfp", "super", "switch", "synchronized", "this", "throw", "throws",
+                "transient", "try", "void", "volatile", "while",
+            ],
+            Language::Cpp => &[
+                "alignas", "alignof", "auto", "bool", "break", "case", "catch", "char",
+                "class", "const", "constexpr", "const_cast", "continue", "decltype",
+                "default", "delete", "do", "double", "dynamic_cast", "else", "enum",
+                "explicit", "export", "extern", "float", "for", "friend", "goto", "if",
+                "inline", "int", "long", "mutable", "namespace", "new", "noexcept",
+                "nullptr", "operator", "private", "protected", "public", "reinterpret_cast",
+                "return", "short", "signed", "sizeof", "static", "static_assert",
+                "static_cast", "struct", "switch", "template", "this", "throw", "try",
+                "typedef", "typename", "union", "unsigned", "using", "virtual", "void",
+                "volatile", "while",
+            ],
+            Language::CSharp => &[
+                "abstract", "as", "base", "bool", "break", "byte", "case", "catch", "char",
+                "checked", "class", "const", "continue", "decimal", "default", "delegate",
+                "do", "double", "else", "enum", "event", "explicit", "extern", "finally",
+                "fixed", "float", "for", "foreach", "goto", "if", "implicit", "in", "int",
+                "interface", "internal", "is", "lock", "long", "namespace", "new", "null",
+                "object", "operator", "out", "override", "params", "private", "protected",
+                "public", "readonly", "ref", "return", "sbyte", "sealed", "short", "sizeof",
+                "stackalloc", "static", "string", "struct", "switch", "this", "throw", "try",
+                "typeof", "uint", "ulong", "unchecked", "unsafe", "ushort", "using",
+                "virtual", "void", "volatile", "while", "async", "await", "var",
+            ],
+            Language::C => &[
+                "auto", "break", "case", "char", "const", "continue", "default", "do",
+                "double", "else", "enum", "extern", "float", "for", "goto", "if", "inline",
+                "int", "long", "register", "restrict", "return", "short", "signed",
+                "sizeof", "static", "struct", "switch", "typedef", "union", "unsigned",
+                "void", "volatile", "while",
+            ],
+            Language::Ruby => &[
+                "alias", "and", "begin", "break", "case", "class", "def", "defined",
+                "do", "else", "elsif", "end", "ensure", "false", "for", "if", "in",
+                "module", "next", "nil", "not", "or", "redo", "rescue", "retry",
+                "return", "self", "super", "then", "true", "undef", "unless", "until",
+                "when", "while", "yield",
+            ],
             _ => &[],
         }
     }
+    
+    /// 关键字是否区分大小写 (PHP 的关键字不区分)
+    pub fn is_case_sensitive(&self) -> bool {
+        !matches!(self, Language::Php)
+    }
+    
+    /// 字符能否出现在标识符中
+    ///
+    /// 除字母、数字与下划线外，JavaScript、TypeScript 与 PHP 的标识符还可以包含 `$`。
+    pub fn is_identifier_char(&self, c: char) -> bool {
+        match self {
+            Language::JavaScript | Language::TypeScript | Language::Php => {
+                c.is_alphanumeric() || c == '_' || c == '$'
+            }
+            _ => c.is_alphanumeric() || c == '_',
+        }
+    }
+    
+    /// 行注释标记
+    pub fn line_comment(&self) -> Option<&'static str> {
+        match self {
+            Language::Python | Language::Ruby => Some("#"),
+            Language::Unknown => None,
+            _ => Some("//"),
+        }
+    }
+    
+    /// 块注释的起止标记
+    ///
+    /// Ruby 的 `=begin`/`=end` 只能出现在行首，不作为块注释处理。
+    pub fn block_comment(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Language::Python | Language::Ruby | Language::Unknown => None,
+            _ => Some(("/*", "*/")),
+        }
+    }
 }
 
 impl fmt::Display for Language {
@@ -91,12 +314,198 @@ impl fmt::Display for Language {
             Language::Java => "Java",
             Language::Cpp => "C++",
             Language::CSharp => "C#",
+            Language::C => "C",
+            Language::Ruby => "Ruby",
+            Language::Php => "PHP",
+            Language::Swift => "Swift",
+            Language::Kotlin => "Kotlin",
             Language::Unknown => "Unknown",
         };
         write!(f, "{}", name)
     }
 }
 
+impl Serialize for Language {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for Language {
+    /// 无法识别的字符串解析为 `Unknown`，不会使整个反序列化失败
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Ok(Language::from_id(&id))
+    }
+}
+
+/// 文件类别
+///
+/// 与只描述源代码的 [`Language`] 互补，用于文件树图标等场景。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum FileKind {
+    /// 可识别语言的源代码
+    Source,
+    /// 标记语言与文档，如 Markdown、HTML
+    Markup,
+    /// 结构化数据与配置，如 JSON、TOML
+    Data,
+    /// 图片，按文件头魔数识别
+    Image,
+    /// 其他二进制内容
+    Binary,
+    #[default]
+    Other,
+}
+
+impl FileKind {
+    /// 从文件扩展名推断类别 (图片只按内容识别，不会返回 `Image`)
+    pub fn from_extension(ext: &str) -> Self {
+        let ext = ext.to_lowercase();
+        match ext.as_str() {
+            "md" | "markdown" | "rst" | "txt" | "html" | "htm" | "xml" | "svg" | "tex" => FileKind::Markup,
+            "json" | "yaml" | "yml" | "toml" | "csv" | "tsv" | "ini" | "cfg" | "conf" | "lock" => FileKind::Data,
+            _ if Language::from_extension(&ext) != Language::Unknown => FileKind::Source,
+            _ => FileKind::Other,
+        }
+    }
+    
+    /// 综合扩展名、语言与文件开头的字节判断类别
+    ///
+    /// 图片魔数优先于一切；其余二进制内容归为 `Binary`，识别出语言的文本 (含 shebang) 归为 `Source`。
+    pub fn detect(path: &Path, language: Language, head: &[u8], is_binary: bool) -> Self {
+        if is_image_magic(head) {
+            FileKind::Image
+        } else if is_binary {
+            FileKind::Binary
+        } else if language != Language::Unknown {
+            FileKind::Source
+        } else {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(FileKind::from_extension)
+                .unwrap_or(FileKind::Other)
+        }
+    }
+}
+
+/// 扩展名给出的置信度
+const EXTENSION_CONFIDENCE: f32 = 0.6;
+/// shebang 给出的置信度 (略低于扩展名，两者冲突且没有其他证据时以扩展名为准)
+const SHEBANG_CONFIDENCE: f32 = 0.55;
+/// 关键字密度最多给出的置信度，单凭关键字不足以确定语言
+const KEYWORD_CONFIDENCE: f32 = 0.4;
+/// 关键字占标识符的比例达到该值时关键字证据取满分
+const FULL_KEYWORD_DENSITY: f32 = 0.2;
+/// 采用检测结果所需的最低置信度，低于该值时视为 Unknown
+const LANGUAGE_CONFIDENCE_THRESHOLD: f32 = 0.5;
+
+/// 关键字密度统计用的关键字集合，不区分大小写的语言存小写形式
+static KEYWORD_SETS: once_cell::sync::OnceCell<HashMap<Language, HashSet<String>>> =
+    once_cell::sync::OnceCell::new();
+
+fn keyword_sets() -> &'static HashMap<Language, HashSet<String>> {
+    KEYWORD_SETS.get_or_init(|| {
+        Language::ALL.iter()
+            .map(|&language| {
+                let keywords = language.keywords().iter()
+                    .map(|keyword| {
+                        if language.is_case_sensitive() {
+                            keyword.to_string()
+                        } else {
+                            keyword.to_lowercase()
+                        }
+                    })
+                    .collect();
+                (language, keywords)
+            })
+            .collect()
+    })
+}
+
+/// 扩展名对应的语言
+fn language_by_extension(path: &Path) -> Language {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(Language::Unknown, Language::from_extension)
+}
+
+/// `sample` 首行 shebang 对应的语言
+fn language_by_shebang(sample: &str) -> Language {
+    Language::from_content(sample.lines().next().unwrap_or(""))
+}
+
+/// 综合扩展名、shebang 与关键字密度检测语言，返回按置信度从高到低排列的候选
+///
+/// 置信度在 0 到 1 之间，各项证据相加后截断到 1；没有任何证据时返回空列表。
+/// `sample` 为文件开头的一段文本，首行用于 shebang 检测。
+pub fn detect_language(path: &Path, sample: &str) -> Vec<(Language, f32)> {
+    let mut scores: HashMap<Language, f32> = HashMap::new();
+    
+    let by_extension = language_by_extension(path);
+    if by_extension != Language::Unknown {
+        *scores.entry(by_extension).or_default() += EXTENSION_CONFIDENCE;
+    }
+    
+    let by_shebang = language_by_shebang(sample);
+    if by_shebang != Language::Unknown {
+        *scores.entry(by_shebang).or_default() += SHEBANG_CONFIDENCE;
+    }
+    
+    let words: Vec<&str> = sample
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .filter(|word| word.chars().next().is_some_and(|c| !c.is_numeric()))
+        .collect();
+    if words.len() >= 3 {
+        let keyword_sets = keyword_sets();
+        for &language in Language::ALL {
+            let keywords = &keyword_sets[&language];
+            if keywords.is_empty() {
+                continue;
+            }
+            let hits = words.iter()
+                .filter(|word| {
+                    if language.is_case_sensitive() {
+                        keywords.contains(**word)
+                    } else {
+                        keywords.contains(&word.to_lowercase())
+                    }
+                })
+                .count();
+            
+            let density = hits as f32 / words.len() as f32;
+            let confidence = (density / FULL_KEYWORD_DENSITY).min(1.0) * KEYWORD_CONFIDENCE;
+            if confidence > 0.0 {
+                *scores.entry(language).or_default() += confidence;
+            }
+        }
+    }
+    
+    let position = |language: &Language| Language::ALL.iter().position(|l| l == language);
+    let mut candidates: Vec<(Language, f32)> = scores.into_iter()
+        .map(|(language, score)| (language, score.min(1.0)))
+        .collect();
+    candidates.sort_by(|(a, a_score), (b, b_score)| {
+        b_score.total_cmp(a_score).then_with(|| position(a).cmp(&position(b)))
+    });
+    candidates
+}
+
+/// 取置信度最高且不低于阈值的候选，否则为 Unknown
+///
+/// 关键字证据低于阈值，也不足以压过扩展名或 shebang: 只有两者冲突时才需要统计关键字密度。
+fn most_likely_language(path: &Path, sample: &str) -> Language {
+    match (language_by_extension(path), language_by_shebang(sample)) {
+        (Language::Unknown, by_shebang) => by_shebang,
+        (by_extension, Language::Unknown) => by_extension,
+        (by_extension, by_shebang) if by_extension == by_shebang => by_extension,
+        _ => detect_language(path, sample)
+            .first()
+            .filter(|(_, confidence)| *confidence >= LANGUAGE_CONFIDENCE_THRESHOLD)
+            .map_or(Language::Unknown, |&(language, _)| language),
+    }
+}
+
 /// 自定义错误类型
 #[derive(Debug)]
 pub enum ChangoEditorError {
@@ -104,8 +513,14 @@ pub enum ChangoEditorError {
     ParseError(String),
     ValidationError(String),
     NotFound(String),
+    ProjectNotFound(Uuid),
+    FileNotFound(Uuid),
+    PathNotFound(PathBuf),
     PermissionDenied(String),
-    FileTooLarge(usize),
+    FileTooLarge { size: u64, limit: u64 },
+    Cancelled,
+    ProcessorPanic(String),
+    Timeout(PathBuf),
 }
 
 impl fmt::Display for ChangoEditorError {
@@ -115,8 +530,65 @@ impl fmt::Display for ChangoEditorError {
             ChangoEditorError::ParseError(msg) => write!(f, "解析错误: {}", msg),
             ChangoEditorError::ValidationError(msg) => write!(f, "验证错误: {}", msg),
             ChangoEditorError::NotFound(item) => write!(f, "未找到: {}", item),
+            ChangoEditorError::ProjectNotFound(id) => write!(f, "项目不存在: {}", id),
+            ChangoEditorError::FileNotFound(id) => write!(f, "文件不存在: {}", id),
+            ChangoEditorError::PathNotFound(path) => write!(f, "路径不存在: {:?}", path),
             ChangoEditorError::PermissionDenied(msg) => write!(f, "权限被拒绝: {}", msg),
-            ChangoEditorError::FileTooLarge(size) => write!(f, "文件过大: {} 字节", size),
+            ChangoEditorError::FileTooLarge { size, limit } => {
+                write!(f, "文件过大: {} 字节 (上限 {} 字节)", size, limit)
+            }
+            ChangoEditorError::Cancelled => write!(f, "操作已取消"),
+            ChangoEditorError::ProcessorPanic(msg) => write!(f, "处理器崩溃: {}", msg),
+            ChangoEditorError::Timeout(path) => write!(f, "处理超时: {:?}", path),
+        }
+    }
+}
+
+impl ChangoEditorError {
+    /// 稳定的错误代码，供程序判断与日志使用
+    pub fn code(&self) -> &'static str {
+        match self {
+            ChangoEditorError::IoError(_) => "E_IO",
+            ChangoEditorError::ParseError(_) => "E_PARSE",
+            ChangoEditorError::ValidationError(_) => "E_VALIDATION",
+            ChangoEditorError::NotFound(_) => "E_NOT_FOUND",
+            ChangoEditorError::ProjectNotFound(_) => "E_PROJECT_NOT_FOUND",
+            ChangoEditorError::FileNotFound(_) => "E_FILE_NOT_FOUND",
+            ChangoEditorError::PathNotFound(_) => "E_PATH_NOT_FOUND",
+            ChangoEditorError::PermissionDenied(_) => "E_PERMISSION_DENIED",
+            ChangoEditorError::FileTooLarge { .. } => "E_FILE_TOO_LARGE",
+            ChangoEditorError::Cancelled => "E_CANCELLED",
+            ChangoEditorError::ProcessorPanic(_) => "E_PROCESSOR_PANIC",
+            ChangoEditorError::Timeout(_) => "E_TIMEOUT",
+        }
+    }
+    
+    /// 带错误代码的消息快照，如 `[E_IO] IO错误: ...`
+    pub fn to_owned_string(&self) -> String {
+        format!("[{}] {}", self.code(), self)
+    }
+}
+
+/// `io::Error` 本身不能克隆，克隆时保留其 `ErrorKind` 与消息
+impl Clone for ChangoEditorError {
+    fn clone(&self) -> Self {
+        match self {
+            ChangoEditorError::IoError(err) => {
+                ChangoEditorError::IoError(io::Error::new(err.kind(), err.to_string()))
+            }
+            ChangoEditorError::ParseError(msg) => ChangoEditorError::ParseError(msg.clone()),
+            ChangoEditorError::ValidationError(msg) => ChangoEditorError::ValidationError(msg.clone()),
+            ChangoEditorError::NotFound(item) => ChangoEditorError::NotFound(item.clone()),
+            ChangoEditorError::ProjectNotFound(id) => ChangoEditorError::ProjectNotFound(*id),
+            ChangoEditorError::FileNotFound(id) => ChangoEditorError::FileNotFound(*id),
+            ChangoEditorError::PathNotFound(path) => ChangoEditorError::PathNotFound(path.clone()),
+            ChangoEditorError::PermissionDenied(msg) => ChangoEditorError::PermissionDenied(msg.clone()),
+            ChangoEditorError::FileTooLarge { size, limit } => {
+                ChangoEditorError::FileTooLarge { size: *size, limit: *limit }
+            }
+            ChangoEditorError::Cancelled => ChangoEditorError::Cancelled,
+            ChangoEditorError::ProcessorPanic(msg) => ChangoEditorError::ProcessorPanic(msg.clone()),
+            ChangoEditorError::Timeout(path) => ChangoEditorError::Timeout(path.clone()),
         }
     }
 }
@@ -136,9 +608,298 @@ impl From<io::Error> for ChangoEditorError {
     }
 }
 
+impl From<regex::Error> for ChangoEditorError {
+    fn from(err: regex::Error) -> Self {
+        ChangoEditorError::ParseError(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ChangoEditorError {
+    fn from(err: serde_json::Error) -> Self {
+        ChangoEditorError::ParseError(err.to_string())
+    }
+}
+
+/// 遍历错误 (包括符号链接循环) 统一转换为 IO 错误
+impl From<walkdir::Error> for ChangoEditorError {
+    fn from(err: walkdir::Error) -> Self {
+        ChangoEditorError::IoError(err.into())
+    }
+}
+
 /// 类型别名
 type Result<T> = std::result::Result<T, ChangoEditorError>;
 
+/// 换行符风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum LineEnding {
+    /// `\n`，没有任何换行符的文件也归为此类
+    #[default]
+    Lf,
+    /// `\r\n`
+    CrLf,
+    /// 同时存在两种换行符
+    Mixed,
+}
+
+impl LineEnding {
+    /// 换行符对应的字节序列 (Mixed 没有确定的序列)
+    pub fn as_bytes(&self) -> Option<&'static [u8]> {
+        match self {
+            LineEnding::Lf => Some(b"\n"),
+            LineEnding::CrLf => Some(b"\r\n"),
+            LineEnding::Mixed => None,
+        }
+    }
+}
+
+/// 按内容分类的行数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineBreakdown {
+    /// 含有代码的行 (包括代码后跟行尾注释的行)
+    pub code: usize,
+    /// 只有注释的行
+    pub comment: usize,
+    /// 空白行
+    pub blank: usize,
+}
+
+impl std::ops::AddAssign for LineBreakdown {
+    fn add_assign(&mut self, other: LineBreakdown) {
+        self.code += other.code;
+        self.comment += other.comment;
+        self.blank += other.blank;
+    }
+}
+
+/// 校验和算法
+///
+/// SHA-256 适合需要抗碰撞的场景；BLAKE3 与 XXH3 快得多，适合增量扫描的变更检测。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ChecksumAlgorithm {
+    #[default]
+    Sha256,
+    Blake3,
+    XxHash,
+    /// 调用方提供的 [`Checksummer`] 实现 (以其名称区分)，无法由内置算法重新计算
+    Custom(String),
+}
+
+/// 按所选算法增量计算校验和
+enum ChecksumHasher {
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+    XxHash(Box<xxhash_rust::xxh3::Xxh3>),
+}
+
+impl ChecksumHasher {
+    fn new(algorithm: &ChecksumAlgorithm) -> Result<Self> {
+        Ok(match algorithm {
+            ChecksumAlgorithm::Sha256 => ChecksumHasher::Sha256(sha2::Sha256::new()),
+            ChecksumAlgorithm::Blake3 => ChecksumHasher::Blake3(Box::new(blake3::Hasher::new())),
+            ChecksumAlgorithm::XxHash => ChecksumHasher::XxHash(Box::new(xxhash_rust::xxh3::Xxh3::new())),
+            ChecksumAlgorithm::Custom(name) => {
+                return Err(ChangoEditorError::ValidationError(
+                    format!("自定义校验和无法重新计算: {}", name)
+                ));
+            }
+        })
+    }
+    
+    fn update(&mut self, chunk: &[u8]) {
+        match self {
+            ChecksumHasher::Sha256(hasher) => hasher.update(chunk),
+            ChecksumHasher::Blake3(hasher) => {
+                hasher.update(chunk);
+            }
+            ChecksumHasher::XxHash(hasher) => hasher.update(chunk),
+        }
+    }
+    
+    fn finish(self) -> String {
+        match self {
+            ChecksumHasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            ChecksumHasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            ChecksumHasher::XxHash(hasher) => format!("{:016x}", hasher.digest()),
+        }
+    }
+}
+
+/// 可注入的校验和实现，用于测试替身或接入已有的内容寻址存储
+pub trait Checksummer {
+    /// 读取全部内容并返回校验和的文本形式
+    fn hash_reader(&self, reader: &mut dyn Read) -> Result<String>;
+    
+    /// 记录在 `FileInfo::checksum_algorithm` 中的算法，默认以实现类型名区分
+    fn algorithm(&self) -> ChecksumAlgorithm {
+        ChecksumAlgorithm::Custom(std::any::type_name::<Self>().to_string())
+    }
+}
+
+/// 内置算法的实现，与扫描时计算的校验和一致
+impl Checksummer for ChecksumAlgorithm {
+    fn hash_reader(&self, reader: &mut dyn Read) -> Result<String> {
+        let mut hasher = ChecksumHasher::new(self)?;
+        let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => hasher.update(&buffer[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(hasher.finish())
+    }
+    
+    fn algorithm(&self) -> ChecksumAlgorithm {
+        self.clone()
+    }
+}
+
+/// 文件系统元数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsMetadata {
+    pub len: u64,
+    pub is_file: bool,
+    pub is_dir: bool,
+    /// 路径本身是符号链接 (其余字段描述链接目标)
+    pub is_symlink: bool,
+    pub created: Option<SystemTime>,
+    pub modified: Option<SystemTime>,
+}
+
+/// 文件系统抽象，扫描与元数据读取都通过它访问文件
+///
+/// 默认实现 [`StdFileSystem`] 直接访问本地磁盘；[`MemoryFileSystem`] 用于测试，
+/// 也可以实现该 trait 接入远程或压缩包中的项目。
+pub trait FileSystem: Send + Sync + fmt::Debug {
+    fn metadata(&self, path: &Path) -> io::Result<FsMetadata>;
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+    /// 目录的直接子项 (完整路径)，顺序不作保证
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+    /// 写入文件，覆盖已有内容
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+}
+
+/// 本地磁盘文件系统
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdFileSystem;
+
+impl FileSystem for StdFileSystem {
+    fn metadata(&self, path: &Path) -> io::Result<FsMetadata> {
+        let metadata = fs::metadata(path)?;
+        Ok(FsMetadata {
+            len: metadata.len(),
+            is_file: metadata.is_file(),
+            is_dir: metadata.is_dir(),
+            is_symlink: fs::symlink_metadata(path)?.file_type().is_symlink(),
+            created: metadata.created().ok(),
+            modified: metadata.modified().ok(),
+        })
+    }
+    
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(fs::File::open(path)?))
+    }
+    
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+    
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+}
+
+/// 内存文件系统，目录由文件路径隐式构成
+#[derive(Debug, Default)]
+pub struct MemoryFileSystem {
+    files: RwLock<std::collections::BTreeMap<PathBuf, MemoryFile>>,
+}
+
+#[derive(Debug, Clone)]
+struct MemoryFile {
+    contents: Arc<[u8]>,
+    modified: SystemTime,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 写入文件 (覆盖已有内容)，修改时间设为当前时间
+    pub fn insert<P: Into<PathBuf>, C: Into<Vec<u8>>>(&self, path: P, contents: C) {
+        let contents: Arc<[u8]> = contents.into().into();
+        self.files.write().unwrap_or_else(|e| e.into_inner())
+            .insert(path.into(), MemoryFile { contents, modified: SystemTime::now() });
+    }
+    
+    /// 删除文件，文件不存在时返回 false
+    pub fn remove(&self, path: &Path) -> bool {
+        self.files.write().unwrap_or_else(|e| e.into_inner()).remove(path).is_some()
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn metadata(&self, path: &Path) -> io::Result<FsMetadata> {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        if let Some(file) = files.get(path) {
+            return Ok(FsMetadata {
+                len: file.contents.len() as u64,
+                is_file: true,
+                is_dir: false,
+                is_symlink: false,
+                created: Some(file.modified),
+                modified: Some(file.modified),
+            });
+        }
+        
+        if files.keys().any(|file| file != path && file.starts_with(path)) {
+            Ok(FsMetadata {
+                len: 0,
+                is_file: false,
+                is_dir: true,
+                is_symlink: false,
+                created: None,
+                modified: None,
+            })
+        } else {
+            Err(io::Error::new(io::ErrorKind::NotFound, format!("{:?}", path)))
+        }
+    }
+    
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        match files.get(path) {
+            Some(file) => Ok(Box::new(io::Cursor::new(file.contents.clone()))),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, format!("{:?}", path))),
+        }
+    }
+    
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        let children: std::collections::BTreeSet<PathBuf> = files.keys()
+            .filter_map(|file| file.strip_prefix(path).ok())
+            .filter_map(|relative| relative.components().next())
+            .map(|first| path.join(first))
+            .collect();
+        
+        if children.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{:?}", path)));
+        }
+        Ok(children.into_iter().collect())
+    }
+    
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.insert(path, contents);
+        Ok(())
+    }
+}
+
 /// 文件信息结构体
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileInfo {
@@ -148,8 +909,21 @@ pub struct FileInfo {
     pub size: u64,
     pub lines: usize,
     pub language: Language,
+    pub kind: FileKind,
     pub encoding: String,
+    pub line_ending: LineEnding,
+    pub line_breakdown: LineBreakdown,
     pub checksum: String,
+    pub checksum_algorithm: ChecksumAlgorithm,
+    pub is_binary: bool,
+    /// 路径本身是符号链接
+    pub is_symlink: bool,
+    /// 只读取了文件开头的一部分 (预览模式)，行数等统计只覆盖已读取的部分
+    #[serde(default)]
+    pub truncated: bool,
+    /// 匹配项目的 `generated_patterns`，默认不计入统计
+    #[serde(default)]
+    pub is_generated: bool,
     pub created_at: SystemTime,
     pub modified_at: SystemTime,
 }
@@ -157,12 +931,74 @@ pub struct FileInfo {
 impl FileInfo {
     /// 从文件路径创建FileInfo
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_path_with_algorithm(path, ChecksumAlgorithm::default())
+    }
+    
+    /// 从文件路径创建FileInfo，使用指定的校验和算法
+    pub fn from_path_with_algorithm<P: AsRef<Path>>(
+        path: P,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<Self> {
+        Self::read(&StdFileSystem, path.as_ref(), algorithm, MAX_FILE_SIZE, true)
+    }
+    
+    /// 通过给定的文件系统创建FileInfo
+    pub fn from_path_in<P: AsRef<Path>>(file_system: &dyn FileSystem, path: P) -> Result<Self> {
+        Self::read(file_system, path.as_ref(), ChecksumAlgorithm::default(), MAX_FILE_SIZE, true)
+    }
+    
+    /// 从文件路径创建FileInfo，使用调用方提供的校验和实现
+    ///
+    /// `checksum_algorithm` 记为 [`Checksummer::algorithm`]，只与同一实现得到的校验和比较。
+    /// 校验和与其他统计在同一次读取中完成。
+    pub fn from_path_with_checksummer<P: AsRef<Path>>(
+        path: P,
+        checksummer: &dyn Checksummer,
+    ) -> Result<Self> {
         let path = path.as_ref();
-        let metadata = fs::metadata(path)?;
+        Self::read_with(&StdFileSystem, path, checksummer.algorithm(), MAX_FILE_SIZE, |reader| {
+            analyze_with_checksummer(reader, path, checksummer)
+        })
+    }
+    
+    /// 从文件路径创建FileInfo，大于 `limit` 字节的文件返回 `FileTooLarge`
+    pub fn from_path_with_limit<P: AsRef<Path>>(path: P, limit: u64) -> Result<Self> {
+        Self::read(&StdFileSystem, path.as_ref(), ChecksumAlgorithm::default(), limit, true)
+    }
+    
+    /// 快速创建FileInfo: 单次读取统计大小、行数与行分类，不计算校验和
+    ///
+    /// 校验和留空，之后可通过 [`FileInfo::ensure_checksum`] 补齐。
+    pub fn from_path_fast<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read(&StdFileSystem, path.as_ref(), ChecksumAlgorithm::default(), MAX_FILE_SIZE, false)
+    }
+    
+    fn read(
+        file_system: &dyn FileSystem,
+        path: &Path,
+        algorithm: ChecksumAlgorithm,
+        limit: u64,
+        with_checksum: bool,
+    ) -> Result<Self> {
+        let hashed = with_checksum.then(|| algorithm.clone());
+        Self::read_with(file_system, path, algorithm, limit, |reader| {
+            analyze_reader(reader, path, None, hashed.as_ref())
+        })
+    }
+    
+    /// 读取元数据并用 `analyze` 单次遍历内容，`algorithm` 记为文件的校验和算法
+    fn read_with(
+        file_system: &dyn FileSystem,
+        path: &Path,
+        algorithm: ChecksumAlgorithm,
+        limit: u64,
+        analyze: impl FnOnce(Box<dyn Read + Send>) -> Result<ContentSummary>,
+    ) -> Result<Self> {
+        let metadata = file_system.metadata(path)?;
         
         // 检查文件大小
-        if metadata.len() > MAX_FILE_SIZE as u64 {
-            return Err(ChangoEditorError::FileTooLarge(metadata.len() as usize));
+        if metadata.len > limit {
+            return Err(ChangoEditorError::FileTooLarge { size: metadata.len, limit });
         }
         
         let name = path.file_name()
@@ -170,51 +1006,268 @@ impl FileInfo {
             .unwrap_or("unknown")
             .to_string();
         
-        let extension = path.extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("");
-        
-        let language = Language::from_extension(extension);
-        let lines = count_lines(path)?;
-        let checksum = calculate_checksum(path)?;
+        let summary = analyze(file_system.open(path)?)?;
         
         Ok(FileInfo {
             id: Uuid::new_v4(),
             path: path.to_path_buf(),
             name,
-            size: metadata.len(),
-            lines,
-            language,
-            encoding: "utf-8".to_string(),
-            checksum,
-            created_at: metadata.created().unwrap_or(UNIX_EPOCH),
-            modified_at: metadata.modified().unwrap_or(UNIX_EPOCH),
+            size: metadata.len,
+            lines: summary.lines,
+            language: summary.language,
+            kind: FileKind::detect(path, summary.language, &summary.head, summary.is_binary),
+            encoding: summary.encoding,
+            line_ending: summary.line_ending,
+            line_breakdown: summary.line_breakdown,
+            checksum: summary.checksum,
+            checksum_algorithm: algorithm,
+            is_binary: summary.is_binary,
+            is_symlink: metadata.is_symlink,
+            truncated: false,
+            is_generated: false,
+            created_at: metadata.created.unwrap_or(UNIX_EPOCH),
+            modified_at: metadata.modified.unwrap_or(UNIX_EPOCH),
         })
     }
     
-    /// 检查文件是否支持语法高亮
-    pub fn supports_highlighting(&self) -> bool {
-        self.language != Language::Unknown
+    /// 只读预览: 读取文件开头最多 `max_bytes` 字节并解码，不受文件大小上限限制
+    ///
+    /// 大小取自文件系统元数据；文件未读完时 `truncated` 为 true，行数只统计已读取的部分，
+    /// 不计算校验和与行分类。文本文件的行数按解码后的文本统计，二进制文件返回空文本。
+    pub fn preview_from_path<P: AsRef<Path>>(path: P, max_bytes: usize) -> Result<(Self, String)> {
+        Self::preview_from_path_in(&StdFileSystem, path, max_bytes)
     }
     
-    /// 获取相对路径
-    pub fn relative_path(&self, base: &Path) -> Option<PathBuf> {
-        self.path.strip_prefix(base).ok().map(|p| p.to_path_buf())
-    }
-}
-
-/// 项目配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProjectConfig {
-    pub auto_save: bool,
-    pub auto_save_interval: Duration,
-    pub max_backups: usize,
-    pub enable_git: bool,
-    pub exclude_patterns: Vec<String>,
-    pub syntax_themes: HashMap<Language, String>,
-}
-
-impl Default for ProjectConfig {
+    /// 同 [`FileInfo::preview_from_path`]，通过给定的文件系统读取
+    pub fn preview_from_path_in<P: AsRef<Path>>(
+        file_system: &dyn FileSystem,
+        path: P,
+        max_bytes: usize,
+    ) -> Result<(Self, String)> {
+        let path = path.as_ref();
+        let metadata = file_system.metadata(path)?;
+        
+        let mut head = Vec::with_capacity(max_bytes.min(metadata.len as usize));
+        file_system.open(path)?.take(max_bytes as u64).read_to_end(&mut head)?;
+        let truncated = (head.len() as u64) < metadata.len;
+        
+        let is_binary = looks_binary(&head[..head.len().min(BINARY_SNIFF_SIZE)]);
+        let encoding = if is_binary {
+            "binary".to_string()
+        } else {
+            let mut sniffer = EncodingSniffer::new();
+            sniffer.feed(&head);
+            if truncated {
+                sniffer.finish_truncated()
+            } else {
+                sniffer.finish()
+            }
+        };
+        
+        // 截断处的不完整字符不输出替换字符
+        let text = match encoding_rs::Encoding::for_label(encoding.as_bytes()) {
+            Some(decoding) => {
+                let mut decoder = decoding.new_decoder();
+                let capacity = decoder.max_utf8_buffer_length(head.len()).unwrap_or(head.len());
+                let mut text = String::with_capacity(capacity);
+                let _ = decoder.decode_to_string(&head, &mut text, !truncated);
+                text
+            }
+            None => String::new(),
+        };
+        
+        // UTF-16 等编码的换行符不是单字节，统一按解码后的文本统计
+        let mut counter = LineCounter::default();
+        let language = if is_binary {
+            counter.feed(&head);
+            most_likely_language(path, "")
+        } else {
+            counter.feed(text.trim_start_matches('\u{feff}').as_bytes());
+            most_likely_language(path, char_prefix(&text, BINARY_SNIFF_SIZE))
+        };
+        
+        let file_info = FileInfo {
+            id: Uuid::new_v4(),
+            path: path.to_path_buf(),
+            name: path.file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            size: metadata.len,
+            lines: counter.finish(),
+            language,
+            kind: FileKind::detect(path, language, &head, is_binary),
+            encoding,
+            line_ending: counter.line_ending(),
+            line_breakdown: LineBreakdown::default(),
+            checksum: String::new(),
+            checksum_algorithm: ChecksumAlgorithm::default(),
+            is_binary,
+            is_symlink: metadata.is_symlink,
+            truncated,
+            is_generated: false,
+            created_at: metadata.created.unwrap_or(UNIX_EPOCH),
+            modified_at: metadata.modified.unwrap_or(UNIX_EPOCH),
+        };
+        Ok((file_info, text))
+    }
+    
+    /// 保存后用内存中的新内容更新大小、行数与校验和，不重新读取磁盘
+    ///
+    /// `modified_at` 应为写入后文件系统记录的修改时间，以便增量扫描不把刚保存的文件视为已变更；
+    /// 语言保持不变。
+    pub fn update_after_write(&mut self, new_content: &[u8], modified_at: SystemTime) -> Result<()> {
+        // 自定义校验和无法在此重新计算，改用默认算法
+        if let ChecksumAlgorithm::Custom(_) = self.checksum_algorithm {
+            self.checksum_algorithm = ChecksumAlgorithm::default();
+        }
+        let algorithm = Some(&self.checksum_algorithm);
+        let summary = analyze_reader(new_content, &self.path, Some(self.language), algorithm)?;
+        
+        self.line_breakdown = summary.line_breakdown;
+        self.size = new_content.len() as u64;
+        self.lines = summary.lines;
+        self.kind = FileKind::detect(&self.path, self.language, &summary.head, summary.is_binary);
+        self.encoding = summary.encoding;
+        self.line_ending = summary.line_ending;
+        self.checksum = summary.checksum;
+        self.is_binary = summary.is_binary;
+        self.truncated = false;
+        self.modified_at = modified_at;
+        Ok(())
+    }
+    
+    /// 是否已计算校验和
+    pub fn has_checksum(&self) -> bool {
+        !self.checksum.is_empty()
+    }
+    
+    /// 补齐快速扫描时跳过的校验和，已有校验和时不读取文件
+    pub fn ensure_checksum(&mut self) -> Result<()> {
+        self.ensure_checksum_in(&StdFileSystem)
+    }
+    
+    /// 与 `ensure_checksum` 相同，但通过给定的文件系统读取
+    pub fn ensure_checksum_in(&mut self, file_system: &dyn FileSystem) -> Result<()> {
+        if !self.has_checksum() {
+            self.checksum = calculate_checksum(file_system, &self.path, &self.checksum_algorithm)?;
+        }
+        Ok(())
+    }
+    
+    /// 比较两个文件的内容校验和
+    ///
+    /// 两者使用不同算法或任一方尚未计算校验和时不可比，返回 `None` 而不是误报为已修改。
+    pub fn checksum_matches(&self, other: &FileInfo) -> Option<bool> {
+        if !self.has_checksum() || !other.has_checksum() {
+            None
+        } else if self.checksum_algorithm == other.checksum_algorithm {
+            Some(self.checksum == other.checksum)
+        } else {
+            None
+        }
+    }
+    
+    /// 检查文件是否支持语法高亮
+    pub fn supports_highlighting(&self) -> bool {
+        self.language != Language::Unknown && self.encoding != "binary"
+    }
+    
+    /// 获取相对路径
+    pub fn relative_path(&self, base: &Path) -> Option<PathBuf> {
+        self.path.strip_prefix(base).ok().map(|p| p.to_path_buf())
+    }
+    
+    /// 与同一路径的新快照比较，`self` 为旧快照
+    pub fn diff(&self, other: &FileInfo) -> FileDiff {
+        FileDiff {
+            size_delta: other.size as i64 - self.size as i64,
+            line_delta: other.lines as i64 - self.lines as i64,
+            checksum_changed: self.checksum_matches(other).map(|matches| !matches),
+            modified_at: (self.modified_at != other.modified_at)
+                .then_some((self.modified_at, other.modified_at)),
+        }
+    }
+}
+
+/// 同一文件两个快照之间的差异，由 [`FileInfo::diff`] 生成
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FileDiff {
+    /// 大小变化 (字节，新 - 旧)
+    pub size_delta: i64,
+    /// 行数变化 (新 - 旧)
+    pub line_delta: i64,
+    /// 内容是否变化；任一方没有校验和或算法不同时无法判断，为 `None`
+    pub checksum_changed: Option<bool>,
+    /// 修改时间变化时为 (旧, 新)
+    pub modified_at: Option<(SystemTime, SystemTime)>,
+}
+
+impl FileDiff {
+    /// 是否没有任何可观察的变化
+    pub fn is_empty(&self) -> bool {
+        self.size_delta == 0
+            && self.line_delta == 0
+            && self.checksum_changed != Some(true)
+            && self.modified_at.is_none()
+    }
+    
+    /// 内容是否确定发生了变化 (校验和不同，或无法比较校验和时大小/行数不同)
+    pub fn content_changed(&self) -> bool {
+        match self.checksum_changed {
+            Some(changed) => changed,
+            None => self.size_delta != 0 || self.line_delta != 0,
+        }
+    }
+}
+
+/// 项目配置
+///
+/// 反序列化时缺少的字段取默认值，手写的部分配置与旧版本保存的状态文件都能加载。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    pub auto_save: bool,
+    #[serde(with = "duration_secs")]
+    pub auto_save_interval: Duration,
+    pub max_backups: usize,
+    /// 每个文件保留的撤销步数
+    pub max_undo_depth: usize,
+    /// 扫描时允许的最大文件字节数，超过的文件记为 `FileTooLarge` 跳过
+    pub max_file_size: u64,
+    pub enable_git: bool,
+    pub exclude_patterns: Vec<String>,
+    pub syntax_themes: HashMap<Language, String>,
+    /// 扫描时索引的文件扩展名 (带前导点，如 ".rs")
+    ///
+    /// 也可以加入 ".json"、".md" 等非源代码扩展名，这些文件按 [`FileKind`] 归类，不参与语法高亮。
+    pub scanned_extensions: Vec<String>,
+    /// 扫描时遵循 .gitignore / .ignore 规则
+    pub respect_gitignore: bool,
+    /// 扫描时跟随符号链接
+    ///
+    /// 指向祖先目录的循环链接会被检测并跳过。多个路径解析到同一个真实文件时只索引一次，
+    /// 优先保留不经过符号链接的路径。
+    pub follow_symlinks: bool,
+    /// 计算文件校验和使用的算法
+    pub checksum_algorithm: ChecksumAlgorithm,
+    /// 扫描时跳过校验和计算，之后由 [`Project::ensure_checksums`] 补齐
+    pub defer_checksums: bool,
+    /// 生成文件 (锁文件、protobuf 输出等) 的模式，语法与排除模式相同
+    ///
+    /// 匹配的文件仍会被索引，但标记为 `FileInfo::is_generated`。
+    pub generated_patterns: Vec<String>,
+    /// 统计时计入生成文件
+    pub include_generated_in_statistics: bool,
+    /// 扫描时进入以 `.` 开头的文件与目录 (如 `.github/`)
+    ///
+    /// 只影响目录遍历与监听，通过 `add_file` 显式添加的文件不受限制。
+    pub include_hidden: bool,
+    /// `include_hidden` 关闭时仍然进入的隐藏文件与目录 (如 `.github/**`)，语法与排除模式相同
+    pub hidden_include_patterns: Vec<String>,
+}
+
+impl Default for ProjectConfig {
     fn default() -> Self {
         let mut syntax_themes = HashMap::new();
         syntax_themes.insert(Language::Rust, "rust-dark".to_string());
@@ -225,6 +1278,8 @@ impl Default for ProjectConfig {
             auto_save: false,
             auto_save_interval: Duration::from_secs(300),
             max_backups: 5,
+            max_undo_depth: 100,
+            max_file_size: MAX_FILE_SIZE,
             enable_git: true,
             exclude_patterns: vec![
                 "*.tmp".to_string(),
@@ -234,6 +1289,292 @@ impl Default for ProjectConfig {
                 "node_modules/*".to_string(),
             ],
             syntax_themes,
+            scanned_extensions: SUPPORTED_EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+            respect_gitignore: false,
+            follow_symlinks: false,
+            checksum_algorithm: ChecksumAlgorithm::default(),
+            defer_checksums: false,
+            generated_patterns: vec![
+                "*.lock".to_string(),
+                "package-lock.json".to_string(),
+                "*.min.js".to_string(),
+                "*.g.dart".to_string(),
+                "*.pb.go".to_string(),
+                "*.pb.h".to_string(),
+                "*.pb.cc".to_string(),
+                "*_pb2.py".to_string(),
+            ],
+            include_generated_in_statistics: false,
+            include_hidden: false,
+            hidden_include_patterns: Vec::new(),
+        }
+    }
+}
+
+impl ProjectConfig {
+    /// 以默认配置为起点构建自定义配置
+    pub fn builder() -> ProjectConfigBuilder {
+        ProjectConfigBuilder::default()
+    }
+    
+    /// 从配置文件加载，按扩展名识别 `.toml` 或 `.json` 格式
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        
+        match ConfigFormat::from_path(path)? {
+            ConfigFormat::Toml => toml::from_str(&content)
+                .map_err(|e| ChangoEditorError::ParseError(format!("{:?}: {}", path, e))),
+            ConfigFormat::Json => Ok(serde_json::from_str(&content)?),
+        }
+    }
+    
+    /// 保存到配置文件，按扩展名选择 `.toml` 或 `.json` 格式
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        
+        let content = match ConfigFormat::from_path(path)? {
+            ConfigFormat::Toml => toml::to_string_pretty(self)
+                .map_err(|e| ChangoEditorError::ParseError(e.to_string()))?,
+            ConfigFormat::Json => serde_json::to_string_pretty(self)?,
+        };
+        fs::write(path, content)?;
+        Ok(())
+    }
+    
+    /// 加载项目根目录下的 `.chango.toml`，文件不存在时返回 `None`
+    pub fn from_project_dir<P: AsRef<Path>>(dir: P) -> Result<Option<Self>> {
+        let path = dir.as_ref().join(PROJECT_CONFIG_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Self::from_file(path).map(Some)
+    }
+    
+    /// 校验配置，目前检查所有排除模式与生成文件模式都是合法的 glob，且校验和算法是内置算法
+    ///
+    /// 返回的 `ValidationError` 会列出全部无效模式，而不仅是第一个。
+    pub fn validate(&self) -> Result<()> {
+        if let ChecksumAlgorithm::Custom(name) = &self.checksum_algorithm {
+            return Err(ChangoEditorError::ValidationError(
+                format!("项目配置只能使用内置校验和算法: {}", name)
+            ));
+        }
+        validate_patterns(&self.exclude_patterns, "排除模式")?;
+        validate_patterns(&self.generated_patterns, "生成文件模式")?;
+        validate_patterns(&self.hidden_include_patterns, "隐藏文件包含模式")
+    }
+    
+    /// 扫描扩展名集合: 去掉前导点并转为小写，扫描前构建一次，逐文件精确匹配
+    pub fn extension_set(&self) -> HashSet<String> {
+        self.scanned_extensions.iter()
+            .map(|ext| ext.strip_prefix('.').unwrap_or(ext).to_lowercase())
+            .collect()
+    }
+    
+    /// 将排除模式编译为 GlobSet
+    ///
+    /// 匹配对象是相对项目根目录的路径。未以 `/` 开头的模式在任意层级生效，
+    /// 以 `/*` 或 `/**` 结尾的模式同时匹配目录本身，以便扫描时整体剪枝。
+    pub fn exclude_set(&self) -> Result<globset::GlobSet> {
+        build_pattern_set(&self.exclude_patterns, "排除模式")
+    }
+    
+    /// 将生成文件模式编译为 GlobSet，匹配规则与 `exclude_set` 相同
+    pub fn generated_set(&self) -> Result<globset::GlobSet> {
+        build_pattern_set(&self.generated_patterns, "生成文件模式")
+    }
+    
+    /// 扫描与监听时应跳过的隐藏路径判断
+    ///
+    /// `include_hidden` 开启时不跳过任何路径，否则返回的 GlobSet 为隐藏文件包含模式，
+    /// 供 [`is_hidden`] 放行匹配的隐藏文件与目录。
+    fn hidden_filter(&self) -> Result<Option<globset::GlobSet>> {
+        if self.include_hidden {
+            return Ok(None);
+        }
+        build_pattern_set(&self.hidden_include_patterns, "隐藏文件包含模式").map(Some)
+    }
+}
+
+/// 配置文件格式
+enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Ok(ConfigFormat::Toml),
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Ok(ConfigFormat::Json),
+            _ => Err(ChangoEditorError::ValidationError(
+                format!("不支持的配置文件格式: {:?}", path),
+            )),
+        }
+    }
+}
+
+/// 项目配置构建器
+#[derive(Debug, Clone, Default)]
+pub struct ProjectConfigBuilder {
+    config: ProjectConfig,
+}
+
+impl ProjectConfigBuilder {
+    pub fn auto_save(mut self, enabled: bool) -> Self {
+        self.config.auto_save = enabled;
+        self
+    }
+    
+    pub fn auto_save_interval(mut self, interval: Duration) -> Self {
+        self.config.auto_save_interval = interval;
+        self
+    }
+    
+    pub fn max_backups(mut self, count: usize) -> Self {
+        self.config.max_backups = count;
+        self
+    }
+    
+    pub fn max_undo_depth(mut self, depth: usize) -> Self {
+        self.config.max_undo_depth = depth;
+        self
+    }
+    
+    pub fn max_file_size(mut self, limit: u64) -> Self {
+        self.config.max_file_size = limit;
+        self
+    }
+    
+    pub fn enable_git(mut self, enabled: bool) -> Self {
+        self.config.enable_git = enabled;
+        self
+    }
+    
+    /// 追加一条排除模式
+    pub fn exclude_pattern<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.config.exclude_patterns.push(pattern.into());
+        self
+    }
+    
+    /// 替换全部排除模式
+    pub fn exclude_patterns<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.exclude_patterns = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+    
+    /// 设置某种语言使用的语法主题
+    pub fn syntax_theme<S: Into<String>>(mut self, language: Language, theme: S) -> Self {
+        self.config.syntax_themes.insert(language, theme.into());
+        self
+    }
+    
+    /// 追加一个扫描扩展名 (带或不带前导点均可)
+    pub fn scanned_extension<S: AsRef<str>>(mut self, extension: S) -> Self {
+        let extension = extension.as_ref();
+        let extension = if extension.starts_with('.') {
+            extension.to_string()
+        } else {
+            format!(".{}", extension)
+        };
+        self.config.scanned_extensions.push(extension);
+        self
+    }
+    
+    pub fn respect_gitignore(mut self, enabled: bool) -> Self {
+        self.config.respect_gitignore = enabled;
+        self
+    }
+    
+    pub fn follow_symlinks(mut self, enabled: bool) -> Self {
+        self.config.follow_symlinks = enabled;
+        self
+    }
+    
+    pub fn checksum_algorithm(mut self, algorithm: ChecksumAlgorithm) -> Self {
+        self.config.checksum_algorithm = algorithm;
+        self
+    }
+    
+    pub fn defer_checksums(mut self, enabled: bool) -> Self {
+        self.config.defer_checksums = enabled;
+        self
+    }
+    
+    /// 追加一个生成文件模式
+    pub fn generated_pattern<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.config.generated_patterns.push(pattern.into());
+        self
+    }
+    
+    pub fn include_generated_in_statistics(mut self, enabled: bool) -> Self {
+        self.config.include_generated_in_statistics = enabled;
+        self
+    }
+    
+    pub fn include_hidden(mut self, enabled: bool) -> Self {
+        self.config.include_hidden = enabled;
+        self
+    }
+    
+    /// 追加一个隐藏文件包含模式
+    pub fn hidden_include_pattern<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.config.hidden_include_patterns.push(pattern.into());
+        self
+    }
+    
+    pub fn build(self) -> ProjectConfig {
+        self.config
+    }
+}
+
+/// `Project::format_tree` 使用的目录树节点
+#[derive(Default)]
+struct TreeNode {
+    dirs: std::collections::BTreeMap<String, TreeNode>,
+    /// (文件名, 注释)
+    files: Vec<(String, String)>,
+}
+
+impl TreeNode {
+    fn insert(&mut self, path: &Path, annotation: String) {
+        let mut components: Vec<String> = path.components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let name = match components.pop() {
+            Some(name) => name,
+            None => return,
+        };
+        
+        let mut node = self;
+        for component in components {
+            node = node.dirs.entry(component).or_default();
+        }
+        node.files.push((name, annotation));
+    }
+    
+    /// 目录在前、文件在后，各自按名称排序
+    fn render(&mut self, prefix: &str, out: &mut String) {
+        self.files.sort();
+        let total = self.dirs.len() + self.files.len();
+        let mut index = 0;
+        
+        for (name, child) in self.dirs.iter_mut() {
+            index += 1;
+            let last = index == total;
+            out.push_str(&format!("{}{}{}/\n", prefix, if last { "└── " } else { "├── " }, name));
+            child.render(&format!("{}{}", prefix, if last { "    " } else { "│   " }), out);
+        }
+        
+        for (name, annotation) in &self.files {
+            index += 1;
+            let connector = if index == total { "└── " } else { "├── " };
+            out.push_str(&format!("{}{}{}{}\n", prefix, connector, name, annotation));
         }
     }
 }
@@ -248,774 +1589,7523 @@ pub struct Project {
     pub config: ProjectConfig,
     files: RwLock<HashMap<Uuid, FileInfo>>,
     file_index: RwLock<HashMap<PathBuf, Uuid>>,
+    dirty_files: RwLock<HashSet<Uuid>>,
     created_at: SystemTime,
     updated_at: RwLock<SystemTime>,
+    /// 自定义文件系统，`None` 时直接访问本地磁盘
+    file_system: Option<Arc<dyn FileSystem>>,
+    subscribers: Mutex<Vec<crossbeam_channel::Sender<ProjectEvent>>>,
+    /// 编译好的 `config.generated_patterns`
+    generated_set: globset::GlobSet,
+}
+
+/// 每个订阅者可缓冲的项目事件数，缓冲区满时新事件被丢弃
+const PROJECT_EVENT_CAPACITY: usize = 1024;
+
+/// 分批扫描时每批读取的文件数，每批读完立即写入索引
+const SCAN_BATCH_SIZE: usize = 64;
+
+/// 项目文件索引的变更事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProjectEvent {
+    FileAdded(Uuid),
+    FileRemoved(Uuid),
+    FileRenamed { id: Uuid, old: PathBuf, new: PathBuf },
+    /// 一次扫描或增量重新扫描完成，订阅者应重新读取索引
+    ///
+    /// 全量扫描与压缩包扫描不逐个发送 `FileAdded`，只发送此事件；
+    /// 增量扫描的变更多到可能填满缓冲区时同样只发送此事件，保证它不会被丢弃。
+    Rescanned,
 }
 
 impl Project {
     /// 创建新项目
+    ///
+    /// 项目根目录下存在 `.chango.toml` 时使用其中的配置，否则使用默认配置。
     pub fn new<S: Into<String>, P: AsRef<Path>>(
         name: S,
         description: S,
         path: P,
+    ) -> Result<Self> {
+        let config = ProjectConfig::from_project_dir(&path)?.unwrap_or_default();
+        Self::with_config(name, description, path, config)
+    }
+    
+    /// 使用自定义配置创建新项目
+    pub fn with_config<S: Into<String>, P: AsRef<Path>>(
+        name: S,
+        description: S,
+        path: P,
+        config: ProjectConfig,
     ) -> Result<Self> {
         let path = path.as_ref().to_path_buf();
         
         if !path.exists() {
-            return Err(ChangoEditorError::NotFound(format!("路径不存在: {:?}", path)));
+            return Err(ChangoEditorError::PathNotFound(path));
         }
         
         if !path.is_dir() {
             return Err(ChangoEditorError::ValidationError("路径必须是目录".to_string()));
         }
         
+        config.validate()?;
+        let generated_set = config.generated_set()?;
+        
         Ok(Project {
             id: Uuid::new_v4(),
             name: name.into(),
             description: description.into(),
             path,
-            config: ProjectConfig::default(),
+            config,
             files: RwLock::new(HashMap::new()),
             file_index: RwLock::new(HashMap::new()),
+            dirty_files: RwLock::new(HashSet::new()),
             created_at: SystemTime::now(),
             updated_at: RwLock::new(SystemTime::now()),
+            file_system: None,
+            subscribers: Mutex::new(Vec::new()),
+            generated_set,
         })
     }
     
-    /// 扫描项目文件
-    pub fn scan_files(&self) -> Result<usize> {
-        let mut file_count = 0;
+    /// 使用自定义文件系统创建项目
+    ///
+    /// 此时扫描不应用 `.gitignore` 规则，也不跟随符号链接；这样的项目不能用 [`ProjectWatcher`] 监听。
+    pub fn with_file_system<S: Into<String>, P: AsRef<Path>>(
+        name: S,
+        description: S,
+        path: P,
+        config: ProjectConfig,
+        file_system: Arc<dyn FileSystem>,
+    ) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
         
-        for entry in walkdir::WalkDir::new(&self.path)
-            .follow_links(false)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            if entry.file_type().is_file() {
-                if let Some(extension) = entry.path().extension() {
-                    if SUPPORTED_EXTENSIONS.iter().any(|&ext| {
-                        extension.to_str().unwrap_or("").ends_with(&ext[1..])
-                    }) {
-                        match FileInfo::from_path(entry.path()) {
-                            Ok(file_info) => {
-                                self.add_file(file_info)?;
-                                file_count += 1;
-                            }
-                            Err(e) => {
-                                eprintln!("跳过文件 {:?}: {}", entry.path(), e);
-                            }
-                        }
-                    }
-                }
+        match file_system.metadata(&path) {
+            Ok(metadata) if metadata.is_dir => {}
+            Ok(_) => return Err(ChangoEditorError::ValidationError("路径必须是目录".to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(ChangoEditorError::PathNotFound(path));
             }
+            Err(e) => return Err(e.into()),
         }
         
-        self.update_timestamp();
-        Ok(file_count)
+        config.validate()?;
+        let generated_set = config.generated_set()?;
+        
+        Ok(Project {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            description: description.into(),
+            path,
+            config,
+            files: RwLock::new(HashMap::new()),
+            file_index: RwLock::new(HashMap::new()),
+            dirty_files: RwLock::new(HashSet::new()),
+            created_at: SystemTime::now(),
+            updated_at: RwLock::new(SystemTime::now()),
+            file_system: Some(file_system),
+            subscribers: Mutex::new(Vec::new()),
+            generated_set,
+        })
     }
     
-    /// 添加文件
-    pub fn add_file(&self, file_info: FileInfo) -> Result<()> {
-        let file_id = file_info.id;
-        let file_path = file_info.path.clone();
-        
-        {
-            let mut files = self.files.write().unwrap();
-            let mut index = self.file_index.write().unwrap();
-            
-            files.insert(file_id, file_info);
-            index.insert(file_path, file_id);
+    /// 项目使用的文件系统
+    pub fn file_system(&self) -> &dyn FileSystem {
+        match &self.file_system {
+            Some(file_system) => file_system.as_ref(),
+            None => &StdFileSystem,
         }
-        
-        self.update_timestamp();
-        Ok(())
     }
     
-    /// 获取文件
-    pub fn get_file(&self, id: &Uuid) -> Option<FileInfo> {
-        self.files.read().unwrap().get(id).cloned()
+    /// 订阅文件索引的变更事件，可以有多个订阅者
+    ///
+    /// 事件以非阻塞方式发送: 接收端处理不及时导致缓冲区已满时丢弃新事件，
+    /// 丢弃接收端即取消订阅，不会拖慢扫描。
+    pub fn subscribe(&self) -> crossbeam_channel::Receiver<ProjectEvent> {
+        let (tx, rx) = crossbeam_channel::bounded(PROJECT_EVENT_CAPACITY);
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner()).push(tx);
+        rx
     }
     
-    /// 通过路径查找文件
-    pub fn find_file_by_path<P: AsRef<Path>>(&self, path: P) -> Option<FileInfo> {
-        let path = path.as_ref();
-        let index = self.file_index.read().unwrap();
-        let files = self.files.read().unwrap();
+    /// 向所有订阅者广播事件，顺带移除已断开的订阅者
+    fn emit(&self, events: impl IntoIterator<Item = ProjectEvent>) {
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        if subscribers.is_empty() {
+            return;
+        }
         
-        index.get(path)
-            .and_then(|id| files.get(id))
-            .cloned()
+        for event in events {
+            subscribers.retain(|tx| {
+                !matches!(tx.try_send(event.clone()), Err(crossbeam_channel::TrySendError::Disconnected(_)))
+            });
+        }
     }
     
-    /// 获取所有文件
-    pub fn get_all_files(&self) -> Vec<FileInfo> {
-        self.files.read().unwrap().values().cloned().collect()
+    /// 从快照恢复项目 (文件索引为空，需要重新扫描)
+    pub fn from_snapshot(snapshot: ProjectSnapshot) -> Result<Self> {
+        let mut project = Project::with_config(
+            snapshot.name,
+            snapshot.description,
+            snapshot.path,
+            snapshot.config,
+        )?;
+        project.id = snapshot.id;
+        project.created_at = snapshot.created_at;
+        Ok(project)
     }
     
-    /// 按语言分组文件
-    pub fn group_by_language(&self) -> HashMap<Language, Vec<FileInfo>> {
-        let mut groups: HashMap<Language, Vec<FileInfo>> = HashMap::new();
-        
-        for file in self.get_all_files() {
-            groups.entry(file.language).or_default().push(file);
+    /// 生成项目的可序列化快照
+    pub fn snapshot(&self) -> ProjectSnapshot {
+        ProjectSnapshot {
+            id: self.id,
+            name: self.name.clone(),
+            description: self.description.clone(),
+            path: self.path.clone(),
+            config: self.config.clone(),
+            created_at: self.created_at,
         }
-        
-        groups
     }
     
-    /// 获取项目统计
-    pub fn get_statistics(&self) -> ProjectStatistics {
-        let files = self.get_all_files();
-        let mut language_stats = HashMap::new();
-        let mut total_lines = 0;
-        let mut total_size = 0;
-        
-        for file in &files {
-            total_lines += file.lines;
-            total_size += file.size;
-            
-            let stats = language_stats.entry(file.language).or_insert(LanguageStats {
-                file_count: 0,
-                line_count: 0,
-                byte_count: 0,
-            });
-            
-            stats.file_count += 1;
-            stats.line_count += file.lines;
-            stats.byte_count += file.size;
-        }
-        
-        ProjectStatistics {
-            total_files: files.len(),
-            total_lines,
-            total_size,
-            language_stats,
-            created_at: self.created_at,
-            updated_at: *self.updated_at.read().unwrap(),
-        }
+    /// 扫描项目文件
+    ///
+    /// 单个文件的失败记录在 `ScanReport::skipped` 中，
+    /// 只有目录遍历本身无法进行等不可恢复的错误才会返回 `Err`。
+    pub fn scan_files(&self) -> Result<ScanReport> {
+        self.scan_files_until(None, &|| false)
     }
     
-    /// 搜索文件
-    pub fn search_files(&self, query: &str) -> Vec<FileInfo> {
-        let query = query.to_lowercase();
+    /// 限制深度的扫描，`max_depth` 为 0 时只扫描项目根目录下的文件
+    pub fn scan_files_with_depth(&self, max_depth: usize) -> Result<ScanReport> {
+        self.scan_files_until(Some(max_depth), &|| false)
+    }
+    
+    /// 扫描压缩包中的源文件，支持 `.zip`、`.tar`、`.tar.gz` 与 `.tgz`
+    ///
+    /// 条目不会解压到磁盘，而是以 `archive.zip!src/main.rs` 形式的虚拟路径加入索引，
+    /// 校验和与行数均由解压后的内容计算。返回加入索引的文件数。
+    /// 虚拟路径不在项目目录中，之后的 `rescan_files` 会将其移除。
+    pub fn scan_archive<P: AsRef<Path>>(&self, archive: P) -> Result<usize> {
+        let archive = archive.as_ref();
+        let exclude_set = self.config.exclude_set()?;
+        let extensions = self.config.extension_set();
+        let limit = self.config.max_file_size;
+        let entries = MemoryFileSystem::new();
         
-        self.get_all_files()
-            .into_iter()
-            .filter(|file| {
-                file.name.to_lowercase().contains(&query) ||
-                file.path.to_string_lossy().to_lowercase().contains(&query)
-            })
-            .collect()
-    }
-    
-    /// 更新时间戳
-    fn update_timestamp(&self) {
-        *self.updated_at.write().unwrap() = SystemTime::now();
-    }
-}
-
-/// 语言统计信息
-#[derive(Debug, Clone)]
-pub struct LanguageStats {
-    pub file_count: usize,
-    pub line_count: usize,
-    pub byte_count: u64,
-}
-
-/// 项目统计信息
-#[derive(Debug, Clone)]
-pub struct ProjectStatistics {
-    pub total_files: usize,
-    pub total_lines: usize,
-    pub total_size: u64,
-    pub language_stats: HashMap<Language, LanguageStats>,
-    pub created_at: SystemTime,
-    pub updated_at: SystemTime,
-}
-
-/// 项目管理器
-pub struct ProjectManager {
-    projects: Arc<RwLock<HashMap<Uuid, Arc<Project>>>>,
-    recent_projects: Arc<Mutex<Vec<Uuid>>>,
-}
-
-impl ProjectManager {
-    /// 创建新的项目管理器
-    pub fn new() -> Self {
-        Self {
-            projects: Arc::new(RwLock::new(HashMap::new())),
-            recent_projects: Arc::new(Mutex::new(Vec::new())),
+        // 过滤条目: 只保留扫描范围内且未被排除的文件，过大的条目不读取
+        let accept = |name: &Path, size: u64| -> bool {
+            let excluded = name.ancestors()
+                .any(|p| !p.as_os_str().is_empty() && exclude_set.is_match(p));
+            if excluded || !is_scanned_extension(&extensions, name) {
+                return false;
+            }
+            if size > limit {
+                log::warn!("跳过压缩包条目 {:?}: 文件过大", name);
+                return false;
+            }
+            true
+        };
+        let prefix = format!("{}!", archive.display());
+        let virtual_path = |name: &Path| {
+            PathBuf::from(format!("{}{}", prefix, name.to_string_lossy().replace('\\', "/")))
+        };
+        
+        let file_name = archive.file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        
+        if file_name.ends_with(".zip") {
+            let mut zip = zip::ZipArchive::new(fs::File::open(archive)?)
+                .map_err(|e| ChangoEditorError::ParseError(format!("无法读取压缩包 {:?}: {}", archive, e)))?;
+            
+            for i in 0..zip.len() {
+                let entry = zip.by_index(i)
+                    .map_err(|e| ChangoEditorError::ParseError(format!("无法读取压缩包 {:?}: {}", archive, e)))?;
+                // enclosed_name 拒绝绝对路径与 `..` 等越界条目
+                let name = match entry.enclosed_name() {
+                    Some(name) if entry.is_file() => name,
+                    _ => continue,
+                };
+                if !accept(&name, entry.size()) {
+                    continue;
+                }
+                
+                let mut contents = Vec::with_capacity(entry.size() as usize);
+                entry.take(limit + 1).read_to_end(&mut contents)?;
+                entries.insert(virtual_path(&name), contents);
+            }
+        } else if file_name.ends_with(".tar") || file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            let file = fs::File::open(archive)?;
+            let reader: Box<dyn Read> = if file_name.ends_with(".tar") {
+                Box::new(file)
+            } else {
+                Box::new(flate2::read::GzDecoder::new(file))
+            };
+            
+            let mut tar = tar::Archive::new(reader);
+            for entry in tar.entries()? {
+                let entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = entry.path()?.into_owned();
+                // 与 zip 一致，跳过绝对路径与包含 `..` 的条目
+                let escapes = name.components().any(|c| {
+                    !matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir)
+                });
+                if escapes {
+                    continue;
+                }
+                if !accept(&name, entry.size()) {
+                    continue;
+                }
+                
+                let mut contents = Vec::with_capacity(entry.size() as usize);
+                entry.take(limit + 1).read_to_end(&mut contents)?;
+                entries.insert(virtual_path(&name), contents);
+            }
+        } else {
+            return Err(ChangoEditorError::ValidationError(
+                format!("不支持的压缩包格式: {:?}", archive)
+            ));
+        }
+        
+        let paths: Vec<PathBuf> = entries.files.read().unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .cloned()
+            .collect();
+        
+        let mut indexed = Vec::with_capacity(paths.len());
+        for path in paths {
+            // 虚拟文件无法事后补算校验和，因此忽略 defer_checksums
+            match FileInfo::read(&entries, &path, self.config.checksum_algorithm.clone(), limit, true) {
+                Ok(mut file_info) if Self::should_index(&file_info) => {
+                    // 生成文件模式按条目在压缩包内的路径匹配
+                    let virtual_path = path.to_string_lossy();
+                    let entry = virtual_path.strip_prefix(prefix.as_str()).unwrap_or(&virtual_path);
+                    file_info.is_generated = self.generated_set.is_match(entry);
+                    
+                    // 重复扫描同一压缩包时保留已有 ID
+                    if let Some(id) = self.find_file_by_path(&path).map(|file| file.id) {
+                        file_info.id = id;
+                    }
+                    indexed.push(file_info);
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("跳过压缩包条目 {:?}: {}", path, e),
+            }
+        }
+        
+        let count = indexed.len();
+        if count > 0 {
+            self.insert_files(indexed);
+            self.update_timestamp();
         }
+        self.emit([ProjectEvent::Rescanned]);
+        Ok(count)
     }
     
-    /// 创建项目
-    pub fn create_project<S: Into<String>, P: AsRef<Path>>(
+    /// 可取消的扫描: 每读取一个文件前检查 `cancelled`，取消时不修改索引
+    fn scan_files_until(
         &self,
-        name: S,
-        description: S,
-        path: P,
-    ) -> Result<Arc<Project>> {
-        let project = Arc::new(Project::new(name, description, path)?);
-        let project_id = project.id;
+        max_depth: Option<usize>,
+        cancelled: &(dyn Fn() -> bool + Sync),
+    ) -> Result<ScanReport> {
+        let mut report = ScanReport::default();
         
-        {
-            let mut projects = self.projects.write().unwrap();
-            projects.insert(project_id, project.clone());
+        // 先收集候选路径，再并行读取元数据 (行数统计与校验和是主要开销)
+        let results: Vec<(PathBuf, Result<FileInfo>)> = self.collect_candidates(max_depth)?
+            .into_par_iter()
+            .map(|path| {
+                let result = if cancelled() {
+                    Err(ChangoEditorError::Cancelled)
+                } else {
+                    self.read_file_info(&path)
+                };
+                (path, result)
+            })
+            .collect();
+        
+        if cancelled() {
+            return Err(ChangoEditorError::Cancelled);
         }
         
-        self.add_to_recent(project_id);
+        let mut indexed = Vec::with_capacity(results.len());
+        for (path, result) in results {
+            match result {
+                Ok(file_info) if Self::should_index(&file_info) => indexed.push(file_info),
+                Ok(_) => {}
+                Err(e) => report.skipped.push((path, e)),
+            }
+        }
+        report.indexed = indexed.len();
         
-        println!("创建项目: {} (ID: {})", project.name, project.id);
-        Ok(project)
-    }
-    
-    /// 获取项目
-    pub fn get_project(&self, id: &Uuid) -> Option<Arc<Project>> {
-        self.projects.read().unwrap().get(id).cloned()
+        // 一次性批量写入索引，避免逐个文件争用写锁；文件可能很多，只发送一个 Rescanned
+        if !indexed.is_empty() {
+            self.insert_files(indexed);
+            self.update_timestamp();
+        }
+        self.emit([ProjectEvent::Rescanned]);
+        Ok(report)
     }
     
-    /// 获取所有项目
-    pub fn get_all_projects(&self) -> Vec<Arc<Project>> {
-        self.projects.read().unwrap().values().cloned().collect()
+    /// 分批扫描: 每批读完立即写入索引，`cancelled` 触发后停止并保留已写入的文件
+    ///
+    /// 与 `scan_files_until` 不同，取消不是错误，返回值中的 `completed` 为 false。
+    fn scan_files_partial(&self, cancelled: &(dyn Fn() -> bool + Sync)) -> Result<PartialScan> {
+        let mut scan = PartialScan::default();
+        scan.completed = self.scan_in_batches(cancelled, |batch, _| {
+            scan.files.extend(batch.files);
+            scan.skipped.extend(batch.skipped);
+        })?;
+        Ok(scan)
     }
     
-    /// 删除项目
-    pub fn remove_project(&self, id: &Uuid) -> Result<()> {
-        let mut projects = self.projects.write().unwrap();
+    /// 分批扫描的公共循环: 每批并行读取后立即写入索引，再把该批结果与候选文件总数交给 `on_batch`
+    ///
+    /// 返回是否在 `cancelled` 触发前处理完全部候选文件；被取消的文件不算跳过。
+    fn scan_in_batches(
+        &self,
+        cancelled: &(dyn Fn() -> bool + Sync),
+        mut on_batch: impl FnMut(ScanBatch, usize),
+    ) -> Result<bool> {
+        // 遍历本身也可能耗时，遍历中途取消时不再读取已收集的候选文件
+        let candidates = self.collect_candidates_until(None, cancelled)?;
+        // 没有候选文件时不会进入批次循环，需单独判断遍历是否被取消
+        let mut interrupted = candidates.is_empty() && cancelled();
         
-        if projects.remove(id).is_some() {
-            self.remove_from_recent(id);
-            println!("删除项目: {}", id);
-            Ok(())
-        } else {
-            Err(ChangoEditorError::NotFound(format!("项目不存在: {}", id)))
+        for batch in candidates.chunks(SCAN_BATCH_SIZE) {
+            if cancelled() {
+                interrupted = true;
+                break;
+            }
+            
+            let results: Vec<(&PathBuf, Result<FileInfo>)> = batch
+                .par_iter()
+                .map(|path| {
+                    let result = if cancelled() {
+                        Err(ChangoEditorError::Cancelled)
+                    } else {
+                        self.read_file_info(path)
+                    };
+                    (path, result)
+                })
+                .collect();
+            
+            let mut indexed = Vec::with_capacity(results.len());
+            let mut skipped = Vec::new();
+            for (path, result) in results {
+                match result {
+                    Ok(file_info) if Self::should_index(&file_info) => indexed.push(file_info),
+                    Ok(_) => {}
+                    Err(ChangoEditorError::Cancelled) => interrupted = true,
+                    Err(e) => skipped.push((path.clone(), e)),
+                }
+            }
+            
+            let mut files = Vec::with_capacity(indexed.len());
+            if !indexed.is_empty() {
+                let ids = self.insert_files(indexed);
+                self.update_timestamp();
+                let stored = self.files.read().unwrap_or_else(|e| e.into_inner());
+                files.extend(ids.iter().filter_map(|id| stored.get(id).cloned()));
+            }
+            on_batch(ScanBatch { files, skipped, processed: batch.len() }, candidates.len());
         }
-    }
-    
-    /// 获取最近项目
-    pub fn get_recent_projects(&self, limit: usize) -> Vec<Arc<Project>> {
-        let recent = self.recent_projects.lock().unwrap();
-        let projects = self.projects.read().unwrap();
         
-        recent
-            .iter()
-            .take(limit)
-            .filter_map(|id| projects.get(id).cloned())
-            .collect()
+        self.emit([ProjectEvent::Rescanned]);
+        Ok(!interrupted)
     }
     
-    /// 添加到最近项目
-    fn add_to_recent(&self, project_id: Uuid) {
-        let mut recent = self.recent_projects.lock().unwrap();
+    /// 增量重新扫描: 仅重新读取修改时间发生变化的文件
+    ///
+    /// 已索引文件保留原有 ID；新出现的文件被添加，消失或无法再读取的文件被移除。
+    pub fn rescan_files(&self) -> Result<ScanDelta> {
+        let known: HashMap<PathBuf, (Uuid, SystemTime)> = {
+            let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+            files.values()
+                .map(|file| (file.path.clone(), (file.id, file.modified_at)))
+                .collect()
+        };
         
-        // 移除已存在的条目
-        recent.retain(|&id| id != project_id);
+        let mut delta = ScanDelta::default();
+        let mut seen = HashSet::new();
+        let mut updates = Vec::new();
         
-        // 添加到开头
-        recent.insert(0, project_id);
+        for path in self.collect_candidates(None)? {
+            if let Some(&(id, modified_at)) = known.get(&path) {
+                let current = self.file_system().metadata(&path)
+                    .ok()
+                    .and_then(|m| m.modified)
+                    .unwrap_or(UNIX_EPOCH);
+                
+                if current == modified_at {
+                    seen.insert(path);
+                    continue;
+                }
+                
+                match self.read_file_info(&path) {
+                    // 变为二进制等不再需要索引的文件按移除处理
+                    Ok(file_info) if !Self::should_index(&file_info) => {}
+                    Ok(mut file_info) => {
+                        file_info.id = id;
+                        delta.modified.push(id);
+                        updates.push(file_info);
+                        seen.insert(path);
+                    }
+                    Err(e) => {
+                        log::warn!("跳过文件 {:?}: {}", path, e);
+                    }
+                }
+            } else {
+                match self.read_file_info(&path) {
+                    Ok(file_info) if Self::should_index(&file_info) => {
+                        delta.added.push(file_info.id);
+                        updates.push(file_info);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::warn!("跳过文件 {:?}: {}", path, e);
+                    }
+                }
+            }
+        }
         
-        // 限制最大数量
-        recent.truncate(10);
+        delta.removed = known.iter()
+            .filter(|(path, _)| !seen.contains(*path))
+            .map(|(_, &(id, _))| id)
+            .collect();
+        
+        {
+            let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+            let mut index = self.file_index.write().unwrap_or_else(|e| e.into_inner());
+            
+            for id in &delta.removed {
+                if let Some(file_info) = files.remove(id) {
+                    index.remove(&file_info.path);
+                }
+            }
+            
+            for file_info in updates {
+                index.insert(file_info.path.clone(), file_info.id);
+                files.insert(file_info.id, file_info);
+            }
+        }
+        
+        if !delta.is_empty() {
+            self.update_timestamp();
+        }
+        self.emit_delta(&delta);
+        Ok(delta)
     }
     
-    /// 从最近项目中移除
-    fn remove_from_recent(&self, project_id: &Uuid) {
-        let mut recent = self.recent_projects.lock().unwrap();
-        recent.retain(|id| id != project_id);
-    }
-}
-
-impl Default for ProjectManager {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-/// 语法高亮器特征
-pub trait SyntaxHighlighter {
-    fn highlight(&self, code: &str, language: Language) -> Result<String>;
-    fn get_keywords(&self, language: Language) -> &[&str];
-}
-
-/// 简单语法高亮器实现
-pub struct SimpleSyntaxHighlighter {
-    keyword_patterns: HashMap<Language, regex::Regex>,
-}
-
-impl SimpleSyntaxHighlighter {
-    pub fn new() -> Result<Self> {
-        let mut keyword_patterns = HashMap::new();
+    /// 借助扫描缓存的增量重新扫描
+    ///
+    /// 修改时间未变的文件直接复用缓存的文件信息，不读取内容；修改时间变化但校验和与缓存一致的文件
+    /// (如检出操作只改动了时间戳) 只更新修改时间，不计入 `modified`；其余文件完整重新读取。
+    /// 缓存中没有的文件以当前索引中的文件信息代替，因此空缓存不会把未变化的文件报告为修改。
+    /// 扫描结束后缓存只包含当前已索引的文件。
+    pub fn rescan_with_cache(&self, cache: &mut ScanCache) -> Result<ScanDelta> {
+        let known: HashMap<PathBuf, FileInfo> = {
+            let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+            files.values()
+                .map(|file| (file.path.clone(), file.clone()))
+                .collect()
+        };
         
-        for &language in &[Language::Rust, Language::Python, Language::JavaScript] {
-            let keywords = language.keywords();
-            let pattern = format!(r"\b({})\b", keywords.join("|"));
-            let regex = regex::Regex::new(&pattern)
-                .map_err(|e| ChangoEditorError::ParseError(e.to_string()))?;
-            keyword_patterns.insert(language, regex);
+        let mut delta = ScanDelta::default();
+        let mut entries = HashMap::new();
+        let mut updates = Vec::new();
+        
+        for path in self.collect_candidates(None)? {
+            let modified_at = match self.file_system().metadata(&path) {
+                Ok(metadata) => metadata.modified.unwrap_or(UNIX_EPOCH),
+                Err(e) => {
+                    log::warn!("跳过文件 {:?}: {}", path, e);
+                    continue;
+                }
+            };
+            
+            let cached = cache.entries.remove(&path)
+                .or_else(|| known.get(&path).cloned())
+                .filter(|cached| cached.checksum_algorithm == self.config.checksum_algorithm);
+            let (mut file_info, content_changed) = match cached {
+                Some(cached) if cached.modified_at == modified_at => (cached, false),
+                Some(mut cached) if cached.has_checksum()
+                    && calculate_checksum(self.file_system(), &path, &cached.checksum_algorithm)
+                        .is_ok_and(|checksum| checksum == cached.checksum) =>
+                {
+                    cached.modified_at = modified_at;
+                    (cached, false)
+                }
+                _ => match self.read_file_info(&path) {
+                    Ok(file_info) if Self::should_index(&file_info) => (file_info, true),
+                    Ok(_) => continue,
+                    Err(e) => {
+                        log::warn!("跳过文件 {:?}: {}", path, e);
+                        continue;
+                    }
+                },
+            };
+            // 生成文件标记取决于当前配置，不沿用缓存中的值
+            file_info.is_generated = self.is_generated(&path);
+            
+            match known.get(&path) {
+                Some(indexed) => {
+                    file_info.id = indexed.id;
+                    if content_changed {
+                        delta.modified.push(indexed.id);
+                    }
+                    if content_changed || indexed.modified_at != file_info.modified_at {
+                        updates.push(file_info.clone());
+                    }
+                }
+                None => {
+                    delta.added.push(file_info.id);
+                    updates.push(file_info.clone());
+                }
+            }
+            entries.insert(path, file_info);
         }
         
-        Ok(Self {
-            keyword_patterns,
-        })
-    }
-}
-
-impl SyntaxHighlighter for SimpleSyntaxHighlighter {
-    fn highlight(&self, code: &str, language: Language) -> Result<String> {
-        if let Some(regex) = self.keyword_patterns.get(&language) {
-            let highlighted = regex.replace_all(code, "<keyword>$1</keyword>");
-            Ok(highlighted.to_string())
-        } else {
-            Ok(code.to_string())
+        delta.removed = known.iter()
+            .filter(|(path, _)| !entries.contains_key(*path))
+            .map(|(_, indexed)| indexed.id)
+            .collect();
+        cache.entries = entries;
+        
+        {
+            let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+            let mut index = self.file_index.write().unwrap_or_else(|e| e.into_inner());
+            
+            for id in &delta.removed {
+                if let Some(file_info) = files.remove(id) {
+                    index.remove(&file_info.path);
+                }
+            }
+            
+            for file_info in updates {
+                index.insert(file_info.path.clone(), file_info.id);
+                files.insert(file_info.id, file_info);
+            }
         }
+        
+        if !delta.is_empty() {
+            self.update_timestamp();
+        }
+        self.emit_delta(&delta);
+        Ok(delta)
     }
     
-    fn get_keywords(&self, language: Language) -> &[&str] {
-        language.keywords()
-    }
-}
-
-/// 文件处理器特征
-pub trait FileProcessor: Send + Sync {
-    fn process(&self, file: &FileInfo) -> Result<()>;
-    fn get_name(&self) -> &str;
-}
-
-/// 并发文件处理器
-pub struct ConcurrentFileProcessor<T: FileProcessor> {
-    processor: Arc<T>,
-    worker_count: usize,
-}
-
-impl<T: FileProcessor + 'static> ConcurrentFileProcessor<T> {
-    pub fn new(processor: T, worker_count: usize) -> Self {
-        Self {
-            processor: Arc::new(processor),
-            worker_count,
+    /// 广播增量扫描的结果
+    fn emit_delta(&self, delta: &ScanDelta) {
+        // 逐个事件可能填满订阅者的缓冲区，此时只发送 Rescanned
+        if delta.added.len() + delta.removed.len() >= PROJECT_EVENT_CAPACITY {
+            self.emit([ProjectEvent::Rescanned]);
+            return;
         }
+        let added = delta.added.iter().map(|&id| ProjectEvent::FileAdded(id));
+        let removed = delta.removed.iter().map(|&id| ProjectEvent::FileRemoved(id));
+        self.emit(added.chain(removed).chain([ProjectEvent::Rescanned]));
     }
     
-    /// 并发处理文件列表
-    pub fn process_files(&self, files: Vec<FileInfo>) -> Result<Vec<Result<()>>> {
-        let (tx, rx) = crossbeam_channel::bounded(files.len());
-        let results = Arc::new(Mutex::new(Vec::new()));
+    /// 遍历项目目录，收集通过排除规则与扩展名过滤的候选文件，`max_depth` 为 `None` 时不限深度
+    fn collect_candidates(&self, max_depth: Option<usize>) -> Result<Vec<PathBuf>> {
+        self.collect_candidates_until(max_depth, &|| false)
+    }
+    
+    /// 同 `collect_candidates`，`cancelled` 触发后停止遍历并返回已收集的候选文件
+    fn collect_candidates_until(
+        &self,
+        max_depth: Option<usize>,
+        cancelled: &(dyn Fn() -> bool + Sync),
+    ) -> Result<Vec<PathBuf>> {
+        // 遍历器的深度 0 是根目录本身，根目录下的文件位于深度 1
+        let walk_depth = max_depth.map(|depth| depth + 1);
+        let exclude_set = self.config.exclude_set()?;
+        let hidden_filter = self.config.hidden_filter()?;
+        let extensions = self.config.extension_set();
+        let mut candidates = Vec::new();
         
-        // 启动工作线程
-        let mut handles = vec![];
-        for worker_id in 0..self.worker_count {
-            let rx = rx.clone();
-            let processor = self.processor.clone();
-            let results = results.clone();
+        if let Some(file_system) = &self.file_system {
+            self.walk_file_system(
+                file_system.as_ref(),
+                &exclude_set,
+                hidden_filter.as_ref(),
+                walk_depth,
+                cancelled,
+                &mut candidates,
+            )?;
+            candidates.retain(|path| is_scanned_extension(&extensions, path));
+            return Ok(candidates);
+        }
+        
+        if self.config.respect_gitignore {
+            // ignore 遍历器会应用 .gitignore (含嵌套)、全局 gitignore 与 .ignore 规则
+            // 隐藏文件由 is_hidden 判断，以便应用隐藏文件包含模式
+            let root = self.path.clone();
+            let walker = ignore::WalkBuilder::new(&self.path)
+                .hidden(false)
+                .follow_links(self.config.follow_symlinks)
+                .require_git(false)
+                .max_depth(walk_depth)
+                .filter_entry(move |e| {
+                    !is_excluded(&exclude_set, &root, e.path())
+                        && !is_hidden(hidden_filter.as_ref(), &root, e.path())
+                })
+                .build();
             
-            let handle = thread::spawn(move || {
-                while let Ok((index, file)) = rx.recv() {
-                    println!("工作线程 {} 处理文件: {}", worker_id, file.name);
-                    let result = processor.process(&file);
-                    
-                    {
-                        let mut results = results.lock().unwrap();
-                        results.push((index, result));
-                    }
+            for entry in walker.filter_map(|e| e.ok()) {
+                if cancelled() {
+                    break;
                 }
-            });
-            
-            handles.push(handle);
+                if entry.file_type().is_some_and(|t| t.is_file()) {
+                    candidates.push(entry.into_path());
+                }
+            }
+        } else {
+            for entry in walkdir::WalkDir::new(&self.path)
+                .follow_links(self.config.follow_symlinks)
+                .max_depth(walk_depth.unwrap_or(usize::MAX))
+                .into_iter()
+                .filter_entry(|e| {
+                    !is_excluded(&exclude_set, &self.path, e.path())
+                        && !is_hidden(hidden_filter.as_ref(), &self.path, e.path())
+                })
+                .filter_map(|e| e.ok())
+            {
+                if cancelled() {
+                    break;
+                }
+                if entry.file_type().is_file() {
+                    candidates.push(entry.into_path());
+                }
+            }
         }
         
-        // 发送任务
-        for (index, file) in files.into_iter().enumerate() {
-            tx.send((index, file)).unwrap();
-        }
-        drop(tx);
+        candidates.retain(|path| is_scanned_extension(&extensions, path));
         
-        // 等待完成
-        for handle in handles {
-            handle.join().unwrap();
+        if self.config.follow_symlinks {
+            dedup_real_paths(&self.path, &mut candidates);
         }
         
-        // 收集结果
-        let mut results = results.lock().unwrap();
-        results.sort_by_key(|(index, _)| *index);
-        
-        Ok(results.into_iter().map(|(_, result)| result).collect())
+        Ok(candidates)
     }
-}
-
-/// 代码格式化器
-pub struct CodeFormatter;
-
-impl FileProcessor for CodeFormatter {
-    fn process(&self, file: &FileInfo) -> Result<()> {
-        match file.language {
-            Language::Rust => {
-                println!("格式化Rust代码: {}", file.name);
-                // 模拟rustfmt处理
-                thread::sleep(Duration::from_millis(100));
-            }
-            Language::Python => {
-                println!("格式化Python代码: {}", file.name);
-                // 模拟black处理
-                thread::sleep(Duration::from_millis(80));
+    
+    /// 通过自定义文件系统广度优先遍历项目目录
+    fn walk_file_system(
+        &self,
+        file_system: &dyn FileSystem,
+        exclude_set: &globset::GlobSet,
+        hidden_filter: Option<&globset::GlobSet>,
+        walk_depth: Option<usize>,
+        cancelled: &(dyn Fn() -> bool + Sync),
+        candidates: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        let mut pending = std::collections::VecDeque::from([(self.path.clone(), 0usize)]);
+        
+        while let Some((dir, depth)) = pending.pop_front() {
+            if cancelled() {
+                break;
             }
-            Language::JavaScript => {
-                println!("格式化JavaScript代码: {}", file.name);
-                // 模拟prettier处理
-                thread::sleep(Duration::from_millis(60));
-            }
-            _ => {
-                println!("跳过不支持的语言: {} ({})", file.name, file.language);
+            let children = match file_system.read_dir(&dir) {
+                Ok(children) => children,
+                Err(e) if dir == self.path => return Err(e.into()),
+                Err(e) => {
+                    log::warn!("跳过目录 {:?}: {}", dir, e);
+                    continue;
+                }
+            };
+            
+            for child in children {
+                if is_excluded(exclude_set, &self.path, &child) || is_hidden(hidden_filter, &self.path, &child) {
+                    continue;
+                }
+                
+                let metadata = match file_system.metadata(&child) {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        log::warn!("跳过文件 {:?}: {}", child, e);
+                        continue;
+                    }
+                };
+                
+                if metadata.is_file {
+                    candidates.push(child);
+                } else if metadata.is_dir && walk_depth.is_none_or(|max| depth + 1 < max) {
+                    pending.push_back((child, depth + 1));
+                }
             }
         }
+        
         Ok(())
     }
     
-    fn get_name(&self) -> &str {
-        "CodeFormatter"
+    /// 按项目配置读取文件元数据
+    fn read_file_info(&self, path: &Path) -> Result<FileInfo> {
+        let mut file_info = FileInfo::read(
+            self.file_system(),
+            path,
+            self.config.checksum_algorithm.clone(),
+            self.config.max_file_size,
+            !self.config.defer_checksums,
+        )?;
+        file_info.is_generated = self.is_generated(path);
+        Ok(file_info)
     }
-}
-
-/// 异步文件服务
-pub struct AsyncFileService {
-    project_manager: Arc<ProjectManager>,
-}
-
-impl AsyncFileService {
-    pub fn new(project_manager: Arc<ProjectManager>) -> Self {
-        Self { project_manager }
+    
+    /// 路径 (相对项目根目录) 是否匹配生成文件模式
+    pub fn is_generated(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.path).unwrap_or(path);
+        self.generated_set.is_match(relative)
     }
     
-    /// 异步扫描项目
-    pub async fn scan_project_async(&self, project_id: Uuid) -> Result<usize> {
-        let project = self.project_manager
-            .get_project(&project_id)
-            .ok_or_else(|| ChangoEditorError::NotFound(format!("项目不存在: {}", project_id)))?;
-        
-        // 在异步上下文中执行CPU密集型任务
-        let project_clone = project.clone();
-        let result = tokio::task::spawn_blocking(move || {
-            project_clone.scan_files()
-        }).await;
-        
-        match result {
-            Ok(count) => count,
-            Err(e) => Err(ChangoEditorError::ParseError(format!("异步任务失败: {}", e))),
+    /// 判断已读取的文件是否应当加入索引
+    ///
+    /// 二进制文件一律跳过；无扩展名的脚本仅当 shebang 能识别出语言时才索引。
+    fn should_index(file_info: &FileInfo) -> bool {
+        if file_info.is_binary {
+            return false;
         }
+        file_info.path.extension().is_some() || file_info.supports_highlighting()
     }
     
-    /// 异步搜索文件
-    pub async fn search_files_async(&self, project_id: Uuid, query: String) -> Result<Vec<FileInfo>> {
-        let project = self.project_manager
-            .get_project(&project_id)
-            .ok_or_else(|| ChangoEditorError::NotFound(format!("项目不存在: {}", project_id)))?;
+    /// 添加文件，返回文件在项目中的 ID
+    ///
+    /// ID 由项目分配 (见 [`Project::file_id`])，传入的 `file_info.id` 会被替换；
+    /// 路径已在索引中时更新原有条目 (沿用其 ID)，不会产生重复。
+    pub fn add_file(&self, mut file_info: FileInfo) -> Result<Uuid> {
+        let file_id = {
+            let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+            let mut index = self.file_index.write().unwrap_or_else(|e| e.into_inner());
+            
+            self.resolve_file_id(&files, &index, &mut file_info);
+            let file_id = file_info.id;
+            index.insert(file_info.path.clone(), file_id);
+            files.insert(file_id, file_info);
+            file_id
+        };
         
-        let project_clone = project.clone();
-        let result = tokio::task::spawn_blocking(move || {
-            project_clone.search_files(&query)
-        }).await;
+        self.update_timestamp();
+        self.emit([ProjectEvent::FileAdded(file_id)]);
+        Ok(file_id)
+    }
+    
+    /// 批量添加文件: 每个写锁只获取一次，更新时间戳一次，ID 分配规则与 `add_file` 相同
+    pub fn add_files(&self, infos: Vec<FileInfo>) -> Result<()> {
+        if infos.is_empty() {
+            return Ok(());
+        }
         
-        match result {
-            Ok(files) => Ok(files),
-            Err(e) => Err(ChangoEditorError::ParseError(format!("搜索失败: {}", e))),
+        let ids = self.insert_files(infos);
+        self.update_timestamp();
+        self.emit(ids.into_iter().map(ProjectEvent::FileAdded));
+        Ok(())
+    }
+    
+    /// 批量写入索引，不更新时间戳也不发送事件，按输入顺序返回分配的 ID
+    fn insert_files(&self, infos: Vec<FileInfo>) -> Vec<Uuid> {
+        let mut ids = Vec::with_capacity(infos.len());
+        let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+        let mut index = self.file_index.write().unwrap_or_else(|e| e.into_inner());
+        
+        files.reserve(infos.len());
+        index.reserve(infos.len());
+        for mut file_info in infos {
+            self.resolve_file_id(&files, &index, &mut file_info);
+            ids.push(file_info.id);
+            index.insert(file_info.path.clone(), file_info.id);
+            files.insert(file_info.id, file_info);
         }
+        ids
     }
-}
-
-// 工具函数
-
-/// 计算文件行数
-fn count_lines<P: AsRef<Path>>(path: P) -> Result<usize> {
-    let file = fs::File::open(path)?;
-    let reader = BufReader::new(file);
-    Ok(reader.lines().count())
-}
-
-/// 计算文件校验和
-fn calculate_checksum<P: AsRef<Path>>(path: P) -> Result<String> {
-    let content = fs::read(path)?;
-    let hash = sha2::Sha256::digest(&content);
-    Ok(format!("{:x}", hash))
-}
-
-/// 性能基准测试
-pub fn benchmark_file_processing() -> Result<()> {
-    println!("=== 性能基准测试 ===");
     
-    // 创建测试项目
-    let temp_dir = std::env::temp_dir().join("chango_editor_benchmark");
-    fs::create_dir_all(&temp_dir)?;
+    /// 由路径确定性地生成文件 ID
+    ///
+    /// 以项目 ID 为 UUIDv5 命名空间，对相对项目根目录、以 `/` 分隔的规范化路径取哈希:
+    /// 同一文件在多次扫描、会话以及项目目录移动后得到相同的 ID，不同项目之间互不相同。
+    pub fn file_id(&self, path: &Path) -> Uuid {
+        let relative = path.strip_prefix(&self.path).unwrap_or(path);
+        let key = relative.components()
+            .filter(|c| !matches!(c, std::path::Component::CurDir))
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        Uuid::new_v5(&self.id, key.as_bytes())
+    }
     
-    let pm = ProjectManager::new();
-    let project = pm.create_project(
-        "基准测试项目",
-        "性能测试项目",
-        &temp_dir,
-    )?;
+    /// 确定待添加文件的 ID: 已索引的路径沿用原 ID，否则使用 `file_id` 生成的 ID；
+    /// 若该 ID 已被其他路径占用 (例如文件重命名后在原路径新建了文件)，改用随机 ID
+    fn resolve_file_id(
+        &self,
+        files: &HashMap<Uuid, FileInfo>,
+        index: &HashMap<PathBuf, Uuid>,
+        file_info: &mut FileInfo,
+    ) {
+        file_info.id = match index.get(&file_info.path) {
+            Some(&id) => id,
+            None => match self.file_id(&file_info.path) {
+                id if files.contains_key(&id) => Uuid::new_v4(),
+                id => id,
+            },
+        };
+    }
     
-    // 生成测试文件
-    let test_files = generate_test_files(&temp_dir, 100)?;
+    /// 移除文件
+    pub fn remove_file(&self, id: &Uuid) -> Result<FileInfo> {
+        let file_info = {
+            let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+            let mut index = self.file_index.write().unwrap_or_else(|e| e.into_inner());
+            
+            let file_info = files.remove(id)
+                .ok_or(ChangoEditorError::FileNotFound(*id))?;
+            index.remove(&file_info.path);
+            file_info
+        };
+        
+        self.dirty_files.write().unwrap_or_else(|e| e.into_inner()).remove(id);
+        self.update_timestamp();
+        self.emit([ProjectEvent::FileRemoved(*id)]);
+        Ok(file_info)
+    }
     
-    // 添加文件到项目
-    for file_path in test_files {
-        if let Ok(file_info) = FileInfo::from_path(&file_path) {
-            project.add_file(file_info)?;
+    /// 标记文件有未保存的修改
+    pub fn mark_dirty(&self, id: &Uuid) -> Result<()> {
+        if !self.files.read().unwrap_or_else(|e| e.into_inner()).contains_key(id) {
+            return Err(ChangoEditorError::FileNotFound(*id));
         }
+        
+        self.dirty_files.write().unwrap_or_else(|e| e.into_inner()).insert(*id);
+        Ok(())
     }
     
-    // 基准测试：文件扫描
-    let start = Instant::now();
-    let file_count = project.scan_files()?;
-    let scan_duration = start.elapsed();
-    
-    println!("扫描 {} 个文件耗时: {:?}", file_count, scan_duration);
-    
-    // 基准测试：文件搜索
-    let start = Instant::now();
-    let results = project.search_files("test");
-    let search_duration = start.elapsed();
-    
-    println!("搜索找到 {} 个文件，耗时: {:?}", results.len(), search_duration);
+    /// 清除文件的未保存标记，返回之前是否为脏
+    pub fn clear_dirty(&self, id: &Uuid) -> bool {
+        self.dirty_files.write().unwrap_or_else(|e| e.into_inner()).remove(id)
+    }
     
-    // 基准测试：并发处理
-    let files = project.get_all_files();
-    let formatter = CodeFormatter;
-    let processor = ConcurrentFileProcessor::new(formatter, 4);
+    /// 文件是否有未保存的修改
+    pub fn is_dirty(&self, id: &Uuid) -> bool {
+        self.dirty_files.read().unwrap_or_else(|e| e.into_inner()).contains(id)
+    }
     
-    let start = Instant::now();
-    let results = processor.process_files(files)?;
-    let process_duration = start.elapsed();
+    /// 编辑器保存文件后调用: 用已写入的内容更新文件信息并清除脏标记，不重新读取文件内容
+    ///
+    /// 修改时间取自文件系统元数据，无法获取时使用当前时间。
+    pub fn mark_file_saved(&self, id: &Uuid, new_content: &[u8]) -> Result<()> {
+        self.record_write(id, new_content)?;
+        self.clear_dirty(id);
+        Ok(())
+    }
     
-    let success_count = results.iter().filter(|r| r.is_ok()).count();
-    println!("并发处理 {} 个文件成功，耗时: {:?}", success_count, process_duration);
+    /// 文件已写入 `new_content` 后更新索引中的文件信息，不重新读取文件内容
+    fn record_write(&self, id: &Uuid, new_content: &[u8]) -> Result<()> {
+        // 在锁外计算，避免大文件阻塞其他读者
+        let mut file_info = self.get_file(id)
+            .ok_or(ChangoEditorError::FileNotFound(*id))?;
+        let modified_at = self.file_system()
+            .metadata(&file_info.path)
+            .ok()
+            .and_then(|metadata| metadata.modified)
+            .unwrap_or_else(SystemTime::now);
+        file_info.update_after_write(new_content, modified_at)?;
+        
+        {
+            let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+            match files.get_mut(id) {
+                Some(stored) => *stored = file_info,
+                None => return Err(ChangoEditorError::FileNotFound(*id)),
+            }
+        }
+        
+        self.update_timestamp();
+        Ok(())
+    }
     
-    // 清理测试文件
-    fs::remove_dir_all(&temp_dir)?;
+    /// 所有未保存的文件
+    pub fn dirty_files(&self) -> Vec<FileInfo> {
+        let dirty = self.dirty_files.read().unwrap_or_else(|e| e.into_inner());
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        dirty.iter().filter_map(|id| files.get(id).cloned()).collect()
+    }
     
-    Ok(())
-}
-
-/// 生成测试文件
-fn generate_test_files(dir: &Path, count: usize) -> Result<Vec<PathBuf>> {
-    let mut files = Vec::new();
-    let languages = &[Language::Rust, Language::Python, Language::JavaScript];
+    /// 启动自动保存
+    ///
+    /// 仅在 `config.auto_save` 开启时生效：后台线程每隔 `auto_save_interval`
+    /// 对所有脏文件调用通过 `AutoSaveHandle::on_save` 注册的回调，保存成功的文件会被清除脏标记。
+    /// 丢弃返回的句柄即停止自动保存。
+    pub fn start_autosave(self: &Arc<Self>) -> AutoSaveHandle {
+        let callback: Arc<Mutex<Option<SaveCallback>>> = Arc::new(Mutex::new(None));
+        
+        if !self.config.auto_save {
+            return AutoSaveHandle { callback, stop: None, handle: None };
+        }
+        
+        let (stop_tx, stop_rx) = crossbeam_channel::bounded::<()>(0);
+        let project = Arc::downgrade(self);
+        let interval = self.config.auto_save_interval;
+        let thread_callback = callback.clone();
+        
+        let handle = thread::spawn(move || {
+            // 句柄被丢弃时发送端断开，立即退出
+            while let Err(crossbeam_channel::RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                let project = match project.upgrade() {
+                    Some(project) => project,
+                    None => break,
+                };
+                
+                let save = match thread_callback.lock().unwrap_or_else(|e| e.into_inner()).clone() {
+                    Some(save) => save,
+                    None => continue,
+                };
+                
+                for file in project.dirty_files() {
+                    match save(&file) {
+                        Ok(()) => {
+                            project.clear_dirty(&file.id);
+                        }
+                        Err(e) => log::warn!("自动保存失败 {:?}: {}", file.path, e),
+                    }
+                }
+            }
+        });
+        
+        AutoSaveHandle { callback, stop: Some(stop_tx), handle: Some(handle) }
+    }
     
-    for i in 0..count {
-        let lang = languages[i % languages.len()];
-        let extension = match lang {
-            Language::Rust => "rs",
-            Language::Python => "py", 
-            Language::JavaScript => "js",
-            _ => "txt",
+    /// 重命名/移动文件
+    pub fn rename_file(&self, id: &Uuid, new_path: PathBuf) -> Result<()> {
+        let old_path = {
+            let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+            let mut index = self.file_index.write().unwrap_or_else(|e| e.into_inner());
+            
+            if index.get(&new_path).is_some_and(|existing| existing != id) {
+                return Err(ChangoEditorError::ValidationError(
+                    format!("目标路径已被其他文件占用: {:?}", new_path),
+                ));
+            }
+            
+            let file_info = files.get_mut(id)
+                .ok_or(ChangoEditorError::FileNotFound(*id))?;
+            
+            index.remove(&file_info.path);
+            file_info.name = new_path.file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown")
+                .to_string();
+            
+            // 扩展名变化时重新判断语言，无法识别则沿用原语言
+            let language = Language::detect(&new_path, "");
+            if language != Language::Unknown {
+                file_info.language = language;
+            }
+            
+            let old_path = std::mem::replace(&mut file_info.path, new_path.clone());
+            index.insert(new_path.clone(), *id);
+            old_path
         };
         
-        let filename = format!("test_file_{}.{}", i, extension);
-        let file_path = dir.join(&filename);
+        self.update_timestamp();
+        self.emit([ProjectEvent::FileRenamed { id: *id, old: old_path, new: new_path }]);
+        Ok(())
+    }
+    
+    /// 将文件的换行符统一为指定风格并写回磁盘
+    ///
+    /// 按字节替换换行符，UTF-16 等换行符不是单字节的编码返回 `ValidationError`，文件保持不变。
+    /// 是否需要转换由读到的内容决定 (索引中的 `line_ending` 可能已过时)，内容不变时不写回。
+    pub fn normalize_line_endings(&self, id: &Uuid, target: LineEnding) -> Result<()> {
+        let newline = target.as_bytes().ok_or_else(|| {
+            ChangoEditorError::ValidationError("目标换行符不能是 Mixed".to_string())
+        })?;
         
-        let content = generate_sample_code(lang, i);
-        fs::write(&file_path, content)?;
+        let file_info = self.get_file(id)
+            .ok_or(ChangoEditorError::FileNotFound(*id))?;
         
-        files.push(file_path);
+        if text_decoding(&file_info.encoding).is_some() {
+            return Err(ChangoEditorError::ValidationError(
+                format!("不支持转换 {} 编码文件的换行符", file_info.encoding)
+            ));
+        }
+        
+        let mut content = Vec::new();
+        self.file_system().open(&file_info.path)?.read_to_end(&mut content)?;
+        let mut normalized = Vec::with_capacity(content.len());
+        let mut bytes = content.iter().peekable();
+        
+        while let Some(&byte) = bytes.next() {
+            match byte {
+                b'\r' if bytes.peek() == Some(&&b'\n') => {}
+                b'\n' => normalized.extend_from_slice(newline),
+                _ => normalized.push(byte),
+            }
+        }
+        
+        if normalized == content {
+            return Ok(());
+        }
+        
+        self.file_system().write(&file_info.path, &normalized)?;
+        self.record_write(id, &normalized)
     }
     
-    Ok(files)
-}
-
-/// 生成示例代码
-fn generate_sample_code(language: Language, index: usize) -> String {
-    match language {
-        Language::Rust => format!(
-            "// 测试文件 {}\n\
-            fn main() {{\n\
-                println!(\"Hello from Rust file {}!\");\n\
-                let x = {};\n\
-                let y = x * 2;\n\
-                println!(\"Result: {{}}\", y);\n\
-            }}\n",
-            index, index, index
-        ),
-        Language::Python => format!(
-            "# 测试文件 {}\n\
-            def main():\n\
-                print(\"Hello from Python file {}!\")\n\
-                x = {}\n\
-                y = x * 2\n\
-                print(f\"Result: {{y}}\")\n\
-            \n\
-            if __name__ == \"__main__\":\n\
-                main()\n",
-            index, index, index
-        ),
-        Language::JavaScript => format!(
-            "// 测试文件 {}\n\
-            function main() {{\n\
-                console.log(\"Hello from JavaScript file {}!\");\n\
-                const x = {};\n\
-                const y = x * 2;\n\
-                console.log(`Result: ${{y}}`);\n\
-            }}\n\
-            \n\
-            main();\n",
-            index, index, index
-        ),
-        _ => format!("# 未知语言测试文件 {}\n", index),
+    /// 获取文件
+    pub fn get_file(&self, id: &Uuid) -> Option<FileInfo> {
+        self.files.read().unwrap_or_else(|e| e.into_inner()).get(id).cloned()
     }
-}
-
-/// 异步主函数演示
-#[tokio::main]
-async fn async_demo() -> Result<()> {
-    println!("=== 异步功能演示 ===");
     
-    let pm = Arc::new(ProjectManager::new());
-    let service = AsyncFileService::new(pm.clone());
+    /// 读取文件的原始字节
+    ///
+    /// 读取前重新检查文件大小，索引后增长到超过 `max_file_size` 的文件返回 `FileTooLarge`。
+    pub fn read_file_bytes(&self, id: &Uuid) -> Result<Vec<u8>> {
+        let path = self.get_file(id)
+            .ok_or(ChangoEditorError::FileNotFound(*id))?
+            .path;
+        
+        let size = self.file_system().metadata(&path)?.len;
+        if size > self.config.max_file_size {
+            return Err(ChangoEditorError::FileTooLarge { size, limit: self.config.max_file_size });
+        }
+        
+        let file = self.file_system().open(&path)?;
+        let mut bytes = Vec::with_capacity(size as usize);
+        file.take(self.config.max_file_size + 1).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 > self.config.max_file_size {
+            return Err(ChangoEditorError::FileTooLarge {
+                size: bytes.len() as u64,
+                limit: self.config.max_file_size,
+            });
+        }
+        Ok(bytes)
+    }
     
-    // 创建测试项目
-    let temp_dir = std::env::temp_dir().join("chango_editor_async_demo");
-    fs::create_dir_all(&temp_dir)?;
+    /// 按索引时检测到的编码读取文件文本
+    ///
+    /// BOM 优先于记录的编码；二进制文件、未知编码或包含无法解码的字节时返回 `ParseError`。
+    pub fn read_file_contents(&self, id: &Uuid) -> Result<String> {
+        let label = self.get_file(id)
+            .ok_or(ChangoEditorError::FileNotFound(*id))?
+            .encoding;
+        let encoding = encoding_rs::Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| ChangoEditorError::ParseError(format!("无法解码的编码: {}", label)))?;
+        
+        let bytes = self.read_file_bytes(id)?;
+        let (text, used, had_errors) = encoding.decode(&bytes);
+        if had_errors {
+            return Err(ChangoEditorError::ParseError(
+                format!("文件内容不是合法的 {}", used.name())
+            ));
+        }
+        Ok(text.into_owned())
+    }
     
-    let project = pm.create_project(
-        "异步演示项目", 
-        "异步功能测试项目",
-        &temp_dir,
-    )?;
+    /// 重新计算磁盘上文件的校验和并与索引中记录的比较，返回 `false` 表示文件已被外部修改
+    ///
+    /// 快速扫描时尚未计算校验和的文件没有比较基准，使用自定义校验和的文件无法重新计算，
+    /// 两者都返回 `ValidationError`。
+    pub fn verify_checksum(&self, id: &Uuid) -> Result<bool> {
+        let file_info = self.get_file(id).ok_or(ChangoEditorError::FileNotFound(*id))?;
+        if !file_info.has_checksum() {
+            return Err(ChangoEditorError::ValidationError(
+                format!("文件尚未计算校验和: {:?}", file_info.path)
+            ));
+        }
+        
+        let current = calculate_checksum(self.file_system(), &file_info.path, &file_info.checksum_algorithm)?;
+        Ok(current == file_info.checksum)
+    }
     
-    // 生成测试文件
-    generate_test_files(&temp_dir, 50)?;
+    /// 获取文件相对项目根目录的路径
+    ///
+    /// 文件不存在或其路径不在项目根目录下 (如符号链接目标) 时返回 `None`。
+    pub fn relative_path(&self, id: &Uuid) -> Option<PathBuf> {
+        self.files.read().unwrap_or_else(|e| e.into_inner()).get(id)?.relative_path(&self.path)
+    }
     
-    // 异步扫描
-    println!("开始异步扫描...");
-    let start = Instant::now();
-    let file_count = service.scan_project_async(project.id).await?;
-    let duration = start.elapsed();
+    /// 通过路径查找文件
+    pub fn find_file_by_path<P: AsRef<Path>>(&self, path: P) -> Option<FileInfo> {
+        let path = path.as_ref();
+        let index = self.file_index.read().unwrap_or_else(|e| e.into_inner());
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        
+        index.get(path)
+            .and_then(|id| files.get(id))
+            .cloned()
+    }
     
-    println!("异步扫描完成: {} 个文件，耗时: {:?}", file_count, duration);
+    /// 按文件名查找文件 (如 `mod.rs` 可能有多个)，结果按路径排序
+    pub fn find_files_by_name(&self, name: &str) -> Vec<FileInfo> {
+        let mut matches: Vec<FileInfo> = self.files.read().unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|file| file.name == name)
+            .cloned()
+            .collect();
+        matches.sort_by(|a, b| a.path.cmp(&b.path));
+        matches
+    }
     
-    // 异步搜索
-    println!("开始异步搜索...");
-    let start = Instant::now();
-    let results = service.search_files_async(project.id, "test".to_string()).await?;
-    let duration = start.elapsed();
+    /// 按文件名查找唯一的文件
+    ///
+    /// 没有匹配时返回 `NotFound`，存在多个同名文件时返回 `ValidationError`。
+    pub fn find_unique_by_name(&self, name: &str) -> Result<FileInfo> {
+        let mut matches = self.find_files_by_name(name);
+        match matches.len() {
+            0 => Err(ChangoEditorError::NotFound(name.to_string())),
+            1 => Ok(matches.remove(0)),
+            n => Err(ChangoEditorError::ValidationError(
+                format!("文件名不唯一 (ambiguous): {} 有 {} 个匹配", name, n)
+            )),
+        }
+    }
     
-    println!("异步搜索完成: {} 个结果，耗时: {:?}", results.len(), duration);
+    /// 获取所有文件
+    pub fn get_all_files(&self) -> Vec<FileInfo> {
+        self.files.read().unwrap_or_else(|e| e.into_inner()).values().cloned().collect()
+    }
     
-    // 清理
-    fs::remove_dir_all(&temp_dir)?;
+    /// 在读锁内依次访问每个文件，不克隆文件信息
+    ///
+    /// 回调执行期间持有读锁，不要在回调中修改本项目的文件索引。
+    pub fn for_each_file<F: FnMut(&FileInfo)>(&self, mut f: F) {
+        for file in self.files.read().unwrap_or_else(|e| e.into_inner()).values() {
+            f(file);
+        }
+    }
     
-    Ok(())
-}
-
-/// 主函数
-fn main() -> Result<()> {
-    println!("=== {} v{} ===", APP_NAME, APP_VERSION);
-    println!("启动时间: {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"));
+    /// 获取所有文件 ID
+    pub fn file_ids(&self) -> Vec<Uuid> {
+        self.files.read().unwrap_or_else(|e| e.into_inner()).keys().copied().collect()
+    }
     
-    // 解析命令行参数
-    let args: Vec<String> = std::env::args().collect();
+    /// 按已存储的校验和查找内容重复的文件，只返回成员多于一个的分组
+    ///
+    /// 不会重新读取磁盘；使用不同校验和算法的文件不会被归为一组，尚未计算校验和的文件被忽略。
+    pub fn find_duplicates(&self) -> HashMap<String, Vec<FileInfo>> {
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        let mut groups: HashMap<(&ChecksumAlgorithm, &str), Vec<&FileInfo>> = HashMap::new();
+        
+        for file in files.values().filter(|file| file.has_checksum()) {
+            groups.entry((&file.checksum_algorithm, file.checksum.as_str())).or_default().push(file);
+        }
+        
+        groups
+            .into_iter()
+            .filter(|(_, group)| group.len() > 1)
+            .map(|((_, checksum), group)| (checksum.to_string(), group.into_iter().cloned().collect()))
+            .collect()
+    }
     
-    if args.len() < 2 {
-        println!("使用方法:");
-        println!("  {} demo          - 运行功能演示", args[0]);
-        println!("  {} benchmark     - 运行性能基准测试", args[0]);
-        println!("  {} async         - 运行异步功能演示", args[0]);
-        println!("  {} create <name> <path> - 创建项目", args[0]);
-        return Ok(());
+    /// 查找内容相似的文件对，返回 (文件, 文件, 相似度)，按相似度从高到低排列
+    ///
+    /// 相似度是两个文件的行指纹集合 (每连续几行规范化后的哈希) 的 Jaccard 系数，取值 0 到 1。
+    /// 只比较同一语言的文件，跳过二进制、生成文件与无法识别语言的文件。
+    pub fn find_similar(&self, min_similarity: f32) -> Vec<(Uuid, Uuid, f32)> {
+        self.find_similar_with_limit(min_similarity, usize::MAX)
     }
     
-    match args[1].as_str() {
-        "demo" => {
-            // 功能演示
-            println!("\n=== 功能演示 ===");
-            
-            let pm = ProjectManager::new();
+    /// 与 `find_similar` 相同，但每种语言最多比较 `max_files` 个文件 (按路径排序取前若干个)
+    ///
+    /// 每个文件需要读取一次内容，之后两两比较指纹集合，n 个同语言文件的比较次数为 n(n-1)/2，
+    /// 大项目中应设置上限以控制耗时。
+    pub fn find_similar_with_limit(&self, min_similarity: f32, max_files: usize) -> Vec<(Uuid, Uuid, f32)> {
+        type Candidate = (Uuid, PathBuf, Option<&'static encoding_rs::Encoding>);
+        let mut by_language: HashMap<Language, Vec<Candidate>> = HashMap::new();
+        self.for_each_file(|file| {
+            if !file.is_binary && !file.is_generated && file.language != Language::Unknown {
+                by_language.entry(file.language).or_default()
+                    .push((file.id, file.path.clone(), text_decoding(&file.encoding)));
+            }
+        });
+        
+        let mut pairs = Vec::new();
+        for (language, mut files) in by_language {
+            if files.len() > max_files {
+                log::warn!("{} 文件过多，相似度检测只比较前 {} 个", language, max_files);
+                files.sort_by(|a, b| a.1.cmp(&b.1));
+                files.truncate(max_files);
+            }
             
-            // 创建示例项目
-            let project = pm.create_project(
-                "Rust示例项目",
-                "展示Rust语言特性",
-                ".",
-            )?;
+            let fingerprints: Vec<(Uuid, HashSet<u64>)> = files
+                .into_par_iter()
+                .filter_map(|(id, path, decoding)| match self.read_file_bytes(&id) {
+                    // UTF-16 等编码需要先解码，否则按字节拆行得到的指纹毫无意义
+                    Ok(bytes) => Some((id, match decoding {
+                        Some(decoding) => line_shingles(&decoding.decode(&bytes).0),
+                        None => line_shingles(&String::from_utf8_lossy(&bytes)),
+                    })),
+                    Err(e) => {
+                        log::warn!("跳过文件 {:?}: {}", path, e);
+                        None
+                    }
+                })
+                .filter(|(_, shingles)| !shingles.is_empty())
+                .collect();
             
-            // 扫描文件
-            let file_count = project.scan_files()?;
-            println!("扫描到 {} 个文件", file_count);
+            pairs.par_extend((0..fingerprints.len()).into_par_iter().flat_map_iter(|i| {
+                let (a, a_shingles) = &fingerprints[i];
+                fingerprints[i + 1..].iter().filter_map(move |(b, b_shingles)| {
+                    let common = a_shingles.intersection(b_shingles).count();
+                    let similarity = common as f32 / (a_shingles.len() + b_shingles.len() - common) as f32;
+                    (similarity >= min_similarity).then_some((*a, *b, similarity))
+                })
+            }));
+        }
+        
+        pairs.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| (a.0, a.1).cmp(&(b.0, b.1))));
+        pairs
+    }
+    
+    /// 项目指纹: 按相对路径排序后，把每个文件的 (相对路径, 校验和) 依次计入 SHA-256
+    ///
+    /// 结果与扫描顺序和文件 ID 无关，内容相同的两个项目指纹相同。
+    /// 尚未计算校验和的文件按空校验和计入，延迟校验和时应先调用 `ensure_checksums`。
+    pub fn fingerprint(&self) -> String {
+        let mut entries: Vec<(String, String)> = Vec::new();
+        self.for_each_file(|file| {
+            let path = file.relative_path(&self.path).unwrap_or_else(|| file.path.clone());
+            // 统一分隔符，使不同平台上的指纹一致
+            let path = path.to_string_lossy().replace('\\', "/");
+            entries.push((path, file.checksum.clone()));
+        });
+        entries.sort();
+        
+        let mut hasher = ChecksumHasher::Sha256(sha2::Sha256::new());
+        for (path, checksum) in &entries {
+            hasher.update(path.as_bytes());
+            hasher.update(b"\0");
+            hasher.update(checksum.as_bytes());
+            hasher.update(b"\n");
+        }
+        hasher.finish()
+    }
+    
+    /// 用 `workers` 个工作线程对当前索引中的所有文件运行处理器
+    ///
+    /// 与 `ConcurrentFileProcessor::process_files` 按位置返回结果不同，报告中的结果按文件 ID 对应。
+    pub fn process_all<T: FileProcessor + 'static>(&self, processor: T, workers: usize) -> Result<ProcessReport> {
+        let files = self.get_all_files();
+        let ids: Vec<Uuid> = files.iter().map(|file| file.id).collect();
+        
+        let results = ConcurrentFileProcessor::new(processor, workers).process_files(files)?;
+        Ok(ProcessReport {
+            results: ids.into_iter().zip(results).collect(),
+        })
+    }
+    
+    /// 为尚未计算校验和的文件补齐校验和，返回补齐的文件数
+    ///
+    /// 校验和在锁外并行计算，适合在快速扫描之后放到后台任务中执行；
+    /// 计算期间被移除或修改过的文件不会被覆盖。
+    pub fn ensure_checksums(&self) -> usize {
+        let pending: Vec<(Uuid, PathBuf, ChecksumAlgorithm, SystemTime)> = {
+            let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+            files.values()
+                .filter(|file| !file.has_checksum())
+                .map(|file| (file.id, file.path.clone(), file.checksum_algorithm.clone(), file.modified_at))
+                .collect()
+        };
+        
+        let computed: Vec<(Uuid, SystemTime, String)> = pending
+            .into_par_iter()
+            .filter_map(|(id, path, algorithm, modified_at)| {
+                match calculate_checksum(self.file_system(), &path, &algorithm) {
+                    Ok(checksum) => Some((id, modified_at, checksum)),
+                    Err(e) => {
+                        log::warn!("计算校验和失败 {:?}: {}", path, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        
+        let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+        let mut filled = 0;
+        for (id, modified_at, checksum) in computed {
+            if let Some(file) = files.get_mut(&id) {
+                if !file.has_checksum() && file.modified_at == modified_at {
+                    file.checksum = checksum;
+                    filled += 1;
+                }
+            }
+        }
+        filled
+    }
+    
+    /// 按指定度量返回最大的 `n` 个文件，从大到小排列，大小相同时按路径排序
+    ///
+    /// 使用容量为 `n` 的堆做部分排序，只克隆最终入选的文件。
+    pub fn largest_files(&self, n: usize, by: SizeMetric) -> Vec<FileInfo> {
+        if n == 0 {
+            return Vec::new();
+        }
+        
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        // 堆顶是当前入选文件中最"小"的一个，超出容量时将其弹出
+        let mut heap = std::collections::BinaryHeap::with_capacity(n + 1);
+        
+        for file in files.values() {
+            let size = match by {
+                SizeMetric::Bytes => file.size,
+                SizeMetric::Lines => file.lines as u64,
+            };
             
-            // 显示统计信息
-            let stats = project.get_statistics();
-            println!("\n项目统计:");
-            println!("  总文件数: {}", stats.total_files);
-            println!("  总行数: {}", stats.total_lines);
-            println!("  总大小: {} 字节", stats.total_size);
+            heap.push((std::cmp::Reverse(size), &file.path, file.id));
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+        
+        heap.into_sorted_vec()
+            .into_iter()
+            .filter_map(|(_, _, id)| files.get(&id).cloned())
+            .collect()
+    }
+    
+    /// 按语言分组文件
+    pub fn group_by_language(&self) -> HashMap<Language, Vec<FileInfo>> {
+        let mut groups: HashMap<Language, Vec<FileInfo>> = HashMap::new();
+        
+        self.for_each_file(|file| {
+            groups.entry(file.language).or_default().push(file.clone());
+        });
+        
+        groups
+    }
+    
+    /// 指定语言的文件 (分类与 `group_by_language` 一致)，在读锁内过滤后再克隆
+    pub fn files_by_language(&self, language: Language) -> Vec<FileInfo> {
+        self.files.read().unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|file| file.language == language)
+            .cloned()
+            .collect()
+    }
+    
+    /// 指定语言的文件数，不克隆文件信息
+    pub fn count_by_language(&self, language: Language) -> usize {
+        self.files.read().unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|file| file.language == language)
+            .count()
+    }
+    
+    /// 修改时间晚于 `since` 的文件，按修改时间从新到旧排列 (相同时按路径)
+    ///
+    /// 元数据无法提供修改时间的文件记为 `UNIX_EPOCH`，这些文件一律不返回，
+    /// 无论 `since` 取何值。
+    pub fn files_modified_since(&self, since: SystemTime) -> Vec<FileInfo> {
+        let mut files: Vec<FileInfo> = self.files.read().unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|file| file.modified_at != UNIX_EPOCH && file.modified_at > since)
+            .cloned()
+            .collect();
+        files.sort_by(|a, b| b.modified_at.cmp(&a.modified_at).then_with(|| a.path.cmp(&b.path)));
+        files
+    }
+    
+    /// 以 `tree` 风格渲染已索引的文件 (路径相对项目根目录)
+    pub fn format_tree(&self) -> String {
+        self.render_tree(false)
+    }
+    
+    /// 与 `format_tree` 相同，并在每个文件后标注语言与行数
+    pub fn format_tree_annotated(&self) -> String {
+        self.render_tree(true)
+    }
+    
+    fn render_tree(&self, annotate: bool) -> String {
+        let mut root = TreeNode::default();
+        self.for_each_file(|file| {
+            let annotation = if annotate {
+                format!(" ({}, {} 行)", file.language, file.lines)
+            } else {
+                String::new()
+            };
             
-            println!("\n语言分布:");
-            for (language, lang_stats) in stats.language_stats {
-                println!("  {}: {} 个文件, {} 行代码", 
-                    language, lang_stats.file_count, lang_stats.line_count);
+            // 项目根目录之外的文件 (如符号链接目标) 以完整路径挂在根节点下
+            match file.relative_path(&self.path) {
+                Some(path) => root.insert(&path, annotation),
+                None => root.files.push((file.path.display().to_string(), annotation)),
+            }
+        });
+        
+        let mut out = match self.path.file_name() {
+            Some(name) => format!("{}/\n", name.to_string_lossy()),
+            None => format!("{}\n", self.path.display()),
+        };
+        root.render("", &mut out);
+        out
+    }
+    
+    /// 获取项目统计
+    ///
+    /// 除非开启 `include_generated_in_statistics`，生成文件不计入统计。
+    pub fn get_statistics(&self) -> ProjectStatistics {
+        let mut language_stats = HashMap::new();
+        let mut total_files = 0;
+        let mut total_lines = 0;
+        let mut total_size = 0;
+        
+        self.for_each_file(|file| {
+            if file.is_generated && !self.config.include_generated_in_statistics {
+                return;
             }
             
-            // 演示搜索
-            let search_results = project.search_files("test");
-            println!("\n搜索 'test' 找到 {} 个文件", search_results.len());
+            total_files += 1;
+            total_lines += file.lines;
+            total_size += file.size;
             
-            // 演示并发处理
-            let files = project.get_all_files();
-            if !files.is_empty() {
-                println!("\n演示并发处理...");
-                let formatter = CodeFormatter;
-                let processor = ConcurrentFileProcessor::new(formatter, 3);
-                
-                let start = Instant::now();
-                let results = processor.process_files(files.clone())?;
-                let duration = start.elapsed();
-                
-                let success_count = results.iter().filter(|r| r.is_ok()).count();
-                println!("并发处理完成: {}/{} 成功，耗时: {:?}", 
-                    success_count, files.len(), duration);
+            let stats: &mut LanguageStats = language_stats.entry(file.language).or_default();
+            
+            stats.record(file);
+        });
+        
+        ProjectStatistics {
+            total_files,
+            total_lines,
+            total_size,
+            language_stats,
+            created_at: self.created_at,
+            updated_at: *self.updated_at.read().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+    
+    /// 按目录统计，`depth` 为相对项目根目录保留的目录层数
+    ///
+    /// 根目录下的文件 (或 `depth` 为 0 时的全部文件) 归入项目路径本身。
+    pub fn statistics_by_directory(&self, depth: usize) -> HashMap<PathBuf, LanguageStats> {
+        let mut directory_stats = HashMap::new();
+        
+        self.for_each_file(|file| {
+            let relative = file.path.strip_prefix(&self.path).unwrap_or(&file.path);
+            let directory: PathBuf = relative
+                .parent()
+                .map(|parent| parent.components().take(depth).collect())
+                .unwrap_or_default();
+            let directory = if directory.as_os_str().is_empty() {
+                self.path.clone()
+            } else {
+                self.path.join(directory)
+            };
+            
+            let stats: &mut LanguageStats = directory_stats.entry(directory).or_default();
+            
+            stats.record(file);
+        });
+        
+        directory_stats
+    }
+    
+    /// 搜索文件 (按文件名或路径的子串匹配)
+    pub fn search_files(&self, query: &str) -> Vec<FileInfo> {
+        self.search_files_with(query, SearchMode::Substring)
+    }
+    
+    /// 按指定模式搜索文件，模糊模式下结果按匹配得分从高到低排列
+    pub fn search_files_with(&self, query: &str, mode: SearchMode) -> Vec<FileInfo> {
+        self.search_files_until(query, mode, &|| false).unwrap_or_default()
+    }
+    
+    /// 分页搜索文件 (子串匹配)，返回当前页与匹配总数
+    ///
+    /// 结果按路径排序以保证分页稳定；只克隆当前页内的文件信息。
+    pub fn search_files_paged(&self, query: &str, offset: usize, limit: usize) -> (Vec<FileInfo>, usize) {
+        let query = query.to_lowercase();
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        
+        let mut matches: Vec<&FileInfo> = files
+            .values()
+            .filter(|file| {
+                file.name.to_lowercase().contains(&query) ||
+                file.path.to_string_lossy().to_lowercase().contains(&query)
+            })
+            .collect();
+        matches.sort_by(|a, b| a.path.cmp(&b.path));
+        
+        let total = matches.len();
+        let page = matches.into_iter().skip(offset).take(limit).cloned().collect();
+        (page, total)
+    }
+    
+    /// 可取消的文件搜索: 每检查一个文件前检查 `cancelled`
+    fn search_files_until(
+        &self,
+        query: &str,
+        mode: SearchMode,
+        cancelled: &dyn Fn() -> bool,
+    ) -> Result<Vec<FileInfo>> {
+        let query = query.to_lowercase();
+        let mut results = Vec::new();
+        
+        for file in self.get_all_files() {
+            if cancelled() {
+                return Err(ChangoEditorError::Cancelled);
             }
             
-            // 演示语法高亮
-            if let Ok(highlighter) = SimpleSyntaxHighlighter::new() {
-                let sample_code = "fn main() { let x = 42; println!(\"Hello, Rust!\"); }";
-                let highlighted = highlighter.highlight(sample_code, Language::Rust)?;
-                println!("\n语法高亮演示:");
-                println!("原代码: {}", sample_code);
-                println!("高亮后: {}", highlighted);
+            if let Some(score) = self.match_score(&file, &query, mode) {
+                results.push((score, file));
             }
         }
         
-        "benchmark" => {
-            benchmark_file_processing()?;
+        if mode == SearchMode::Fuzzy {
+            results.sort_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then_with(|| a.path.cmp(&b.path)));
         }
         
-        "async" => {
-            // 运行异步演示
-            tokio::runtime::Runtime::new()
-                .unwrap()
-                .block_on(async_demo())?;
+        Ok(results.into_iter().map(|(_, file)| file).collect())
+    }
+    
+    /// 文件与 (已转为小写的) 查询的匹配得分，不匹配时返回 `None`
+    fn match_score(&self, file: &FileInfo, query: &str, mode: SearchMode) -> Option<usize> {
+        let name = file.name.to_lowercase();
+        let path = file.path.to_string_lossy().to_lowercase();
+        
+        match mode {
+            SearchMode::Substring => {
+                (name.contains(query) || path.contains(query)).then_some(0)
+            }
+            SearchMode::AllTerms => {
+                query.split_whitespace()
+                    .all(|term| name.contains(term) || path.contains(term))
+                    .then_some(0)
+            }
+            SearchMode::Fuzzy => {
+                // 文件名命中优先于仅在相对路径中命中
+                fuzzy_score(&name, query).map(|score| score + FUZZY_NAME_BONUS).or_else(|| {
+                    let relative = file.relative_path(&self.path).unwrap_or_else(|| file.path.clone());
+                    fuzzy_score(&relative.to_string_lossy().to_lowercase(), query)
+                })
+            }
         }
+    }
+    
+    /// 流式搜索文件 (与 `search_files` 相同的子串匹配)
+    ///
+    /// 后台线程逐个检查文件，找到匹配即发送，检查完毕后关闭通道。
+    /// 结果不排序；丢弃接收端即可提前停止搜索。
+    pub fn search_files_channel(self: &Arc<Self>, query: String) -> crossbeam_channel::Receiver<FileInfo> {
+        let (tx, rx) = crossbeam_channel::bounded(SEARCH_CHANNEL_CAPACITY);
+        let project = self.clone();
         
-        "create" => {
-            if args.len() < 4 {
-                println!("使用方法: {} create <项目名> <项目路径>", args[0]);
-                return Ok(());
+        thread::spawn(move || {
+            let query = query.to_lowercase();
+            for id in project.file_ids() {
+                // 文件可能在搜索期间被移除
+                let file = match project.get_file(&id) {
+                    Some(file) => file,
+                    None => continue,
+                };
+                
+                if project.match_score(&file, &query, SearchMode::Substring).is_some() && tx.send(file).is_err() {
+                    break;
+                }
             }
+        });
+        
+        rx
+    }
+    
+    /// 在文件内容中搜索子串，返回每一处匹配所在的行
+    pub fn search_contents(&self, query: &str, case_sensitive: bool) -> Result<Vec<ContentMatch>> {
+        self.search_contents_with(&ContentPattern::substring(query, case_sensitive))
+    }
+    
+    /// 使用正则表达式搜索文件内容
+    pub fn search_contents_regex(&self, regex: regex::Regex) -> Result<Vec<ContentMatch>> {
+        self.search_contents_with(&ContentPattern::Regex(regex))
+    }
+    
+    /// 按给定模式逐行搜索文件内容
+    ///
+    /// 文件按需逐行读取，超过 `max_file_size` 的文件与二进制文件会被跳过，
+    /// 索引后已被删除的文件同样忽略。UTF-16 等换行符不是单字节的编码先整体解码再按行匹配。
+    pub fn search_contents_with(&self, pattern: &ContentPattern) -> Result<Vec<ContentMatch>> {
+        let targets: Vec<(Uuid, PathBuf, Option<&'static encoding_rs::Encoding>)> = self.get_all_files()
+            .into_iter()
+            .filter(|file| !file.is_binary)
+            .map(|file| (file.id, file.path, text_decoding(&file.encoding)))
+            .collect();
+        
+        let mut matches = Vec::new();
+        
+        for (file_id, path, decoding) in targets {
+            let file = match self.file_system().metadata(&path) {
+                Ok(metadata) if metadata.len > self.config.max_file_size => continue,
+                Ok(_) => self.file_system().open(&path),
+                Err(e) => Err(e),
+            };
+            let file = match file {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let mut record = |line_number: usize, line: &str| {
+                if pattern.is_match(line) {
+                    matches.push(ContentMatch {
+                        file_id,
+                        line_number,
+                        line: line.to_string(),
+                    });
+                }
+            };
             
-            let name = &args[2];
-            let path = &args[3];
-            
-            let pm = ProjectManager::new();
-            let project = pm.create_project(name, "通过命令行创建", path)?;
+            if let Some(decoding) = decoding {
+                // 文件大小已受 max_file_size 限制，可以一次读入
+                let mut bytes = Vec::new();
+                file.take(self.config.max_file_size).read_to_end(&mut bytes)?;
+                let (text, _, _) = decoding.decode(&bytes);
+                for (index, line) in text.lines().enumerate() {
+                    record(index + 1, line);
+                }
+                continue;
+            }
             
-            println!("项目创建成功: {} (ID: {})", project.name, project.id);
+            let mut reader = BufReader::new(file);
+            let mut buffer = Vec::new();
+            let mut line_number = 0;
             
-            let file_count = project.scan_files()?;
-            println!("扫描到 {} 个文件", file_count);
+            loop {
+                buffer.clear();
+                if reader.read_until(b'\n', &mut buffer)? == 0 {
+                    break;
+                }
+                line_number += 1;
+                
+                let line = String::from_utf8_lossy(&buffer);
+                record(line_number, line.trim_end_matches(&['\r', '\n'][..]));
+            }
         }
         
-        _ => {
-            println!("未知命令: {}", args[1]);
-            return Ok(());
-        }
+        Ok(matches)
     }
     
-    println!("\n程序执行完成!");
-    Ok(())
+    /// 更新时间戳
+    fn update_timestamp(&self) {
+        *self.updated_at.write().unwrap_or_else(|e| e.into_inner()) = SystemTime::now();
+    }
+}
+
+/// 文件大小的度量方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeMetric {
+    Bytes,
+    Lines,
+}
+
+/// 文件搜索模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SearchMode {
+    /// 整个查询作为子串匹配文件名或路径
+    #[default]
+    Substring,
+    /// 按空白拆分查询，所有词都必须匹配 (AND)
+    AllTerms,
+    /// 子序列模糊匹配 (如 `mainrs` 匹配 `main.rs`)，结果按得分排序
+    Fuzzy,
+}
+
+/// 文件内容搜索模式
+#[derive(Debug, Clone)]
+pub enum ContentPattern {
+    /// 子串匹配，大小写不敏感时 needle 已转为小写
+    Substring { needle: String, case_sensitive: bool },
+    /// 正则表达式匹配
+    Regex(regex::Regex),
+}
+
+impl ContentPattern {
+    /// 创建子串匹配模式
+    pub fn substring(query: &str, case_sensitive: bool) -> Self {
+        let needle = if case_sensitive { query.to_string() } else { query.to_lowercase() };
+        ContentPattern::Substring { needle, case_sensitive }
+    }
+    
+    /// 检查一行文本是否匹配
+    pub fn is_match(&self, line: &str) -> bool {
+        match self {
+            ContentPattern::Substring { needle, case_sensitive: true } => line.contains(needle.as_str()),
+            ContentPattern::Substring { needle, case_sensitive: false } => {
+                line.to_lowercase().contains(needle.as_str())
+            }
+            ContentPattern::Regex(regex) => regex.is_match(line),
+        }
+    }
+}
+
+/// 文件内容搜索的单条匹配
+#[derive(Debug, Clone)]
+pub struct ContentMatch {
+    pub file_id: Uuid,
+    /// 行号，从 1 开始
+    pub line_number: usize,
+    pub line: String,
+}
+
+/// 全量扫描结果
+#[derive(Debug, Clone, Default)]
+pub struct ScanReport {
+    /// 成功索引的文件数
+    pub indexed: usize,
+    /// 被跳过的文件及原因
+    pub skipped: Vec<(PathBuf, ChangoEditorError)>,
+}
+
+/// 分批扫描中一批的结果
+struct ScanBatch {
+    /// 本批写入索引的文件
+    files: Vec<FileInfo>,
+    skipped: Vec<(PathBuf, ChangoEditorError)>,
+    /// 本批处理的候选文件数 (无论结果)
+    processed: usize,
+}
+
+/// 限时扫描的结果
+#[derive(Debug, Default)]
+pub struct PartialScan {
+    /// 截止前已写入索引的文件
+    pub files: Vec<FileInfo>,
+    /// 被跳过的文件及原因
+    pub skipped: Vec<(PathBuf, ChangoEditorError)>,
+    /// 是否在截止前扫描完全部文件
+    pub completed: bool,
+}
+
+/// `Project::process_all` 的结果，按文件 ID 对应每个文件的处理结果
+#[derive(Debug, Default)]
+pub struct ProcessReport {
+    pub results: HashMap<Uuid, Result<()>>,
+}
+
+impl ProcessReport {
+    /// 文件的处理结果，文件不在本次处理范围内时返回 `None`
+    pub fn get(&self, id: &Uuid) -> Option<&Result<()>> {
+        self.results.get(id)
+    }
+    
+    /// 处理成功的文件数
+    pub fn succeeded(&self) -> usize {
+        self.results.values().filter(|result| result.is_ok()).count()
+    }
+    
+    /// 处理失败的文件及原因
+    pub fn failures(&self) -> Vec<(Uuid, &ChangoEditorError)> {
+        self.results.iter()
+            .filter_map(|(id, result)| result.as_ref().err().map(|e| (*id, e)))
+            .collect()
+    }
+}
+
+/// 流式扫描的进度事件，序列化为带 `type` 字段的 JSON 对象
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum ScanEvent {
+    FileIndexed { file: FileInfo },
+    Progress { done: usize, total: usize },
+    Done { indexed: usize, skipped: usize },
+    /// `path` 为空表示整个扫描失败
+    Error { path: Option<PathBuf>, code: &'static str, message: String },
+}
+
+impl ScanEvent {
+    fn error(path: Option<PathBuf>, err: &ChangoEditorError) -> Self {
+        ScanEvent::Error {
+            path,
+            code: err.code(),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// 增量扫描的变更集合
+#[derive(Debug, Clone, Default)]
+pub struct ScanDelta {
+    pub added: Vec<Uuid>,
+    pub modified: Vec<Uuid>,
+    pub removed: Vec<Uuid>,
+}
+
+impl ScanDelta {
+    /// 是否没有任何变更
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// 持久化的扫描缓存，供 [`Project::rescan_with_cache`] 使用
+///
+/// 以路径为键保存上次扫描得到的文件信息 (含修改时间与校验和)，可跨进程保存与加载。
+/// 缓存只用于加速，无法解析的缓存文件 (如损坏或来自不兼容的版本) 按空缓存处理。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanCache {
+    entries: HashMap<PathBuf, FileInfo>,
+}
+
+impl ScanCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 从 JSON 文件加载缓存，文件不存在或无法解析时返回空缓存
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json).unwrap_or_else(|e| {
+                log::warn!("忽略无法解析的扫描缓存 {:?}: {}", path, e);
+                Self::default()
+            })),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// 保存缓存到 JSON 文件
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+    
+    /// 获取某个路径的缓存条目
+    pub fn get(&self, path: &Path) -> Option<&FileInfo> {
+        self.entries.get(path)
+    }
+    
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// 项目模板: 创建项目时写入的目录与初始文件
+///
+/// 所有路径都相对于项目根目录；已存在的文件不会被覆盖。
+#[derive(Debug, Clone, Default)]
+pub struct ProjectTemplate {
+    pub name: String,
+    pub directories: Vec<PathBuf>,
+    pub files: Vec<(PathBuf, String)>,
+    /// 写入 `.chango.toml` 的项目配置
+    pub config: Option<ProjectConfig>,
+}
+
+impl ProjectTemplate {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+    
+    /// 添加一个要创建的目录
+    pub fn directory<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.directories.push(path.into());
+        self
+    }
+    
+    /// 添加一个初始文件，父目录会自动创建
+    pub fn file<P: Into<PathBuf>, S: Into<String>>(mut self, path: P, content: S) -> Self {
+        self.files.push((path.into(), content.into()));
+        self
+    }
+    
+    /// 设置写入 `.chango.toml` 的项目配置
+    pub fn config(mut self, config: ProjectConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+    
+    /// 内置的语言模板，不支持的语言返回 `None`
+    pub fn for_language(language: Language) -> Option<Self> {
+        let template = match language {
+            Language::Rust => Self::new("rust")
+                .file("src/main.rs", "fn main() {\n    println!(\"Hello, world!\");\n}\n"),
+            Language::Python => Self::new("python")
+                .directory("tests")
+                .file("main.py", "def main():\n    print(\"Hello, world!\")\n\n\nif __name__ == \"__main__\":\n    main()\n"),
+            Language::JavaScript => Self::new("javascript")
+                .file("src/index.js", "console.log(\"Hello, world!\");\n"),
+            _ => return None,
+        };
+        Some(template.config(ProjectConfig::default()))
+    }
+    
+    /// 在 `root` 下创建模板中的目录与文件
+    fn apply(&self, root: &Path) -> Result<()> {
+        let relative_paths = self.directories.iter().chain(self.files.iter().map(|(path, _)| path));
+        for path in relative_paths {
+            let escapes = path.components().any(|component| {
+                !matches!(component, std::path::Component::Normal(_) | std::path::Component::CurDir)
+            });
+            if escapes {
+                return Err(ChangoEditorError::ValidationError(
+                    format!("模板路径必须位于项目目录内: {:?}", path)
+                ));
+            }
+        }
+        
+        for directory in &self.directories {
+            fs::create_dir_all(root.join(directory))?;
+        }
+        
+        let mut files: Vec<(PathBuf, String)> = self.files.clone();
+        if let Some(config) = &self.config {
+            let content = toml::to_string_pretty(config)
+                .map_err(|e| ChangoEditorError::ParseError(e.to_string()))?;
+            files.push((PathBuf::from(PROJECT_CONFIG_FILE), content));
+        }
+        
+        for (relative, content) in files {
+            let path = root.join(relative);
+            if path.exists() {
+                log::debug!("模板文件已存在，跳过: {:?}", path);
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)?;
+        }
+        
+        Ok(())
+    }
+}
+
+/// 项目的可序列化快照 (不含文件索引)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectSnapshot {
+    pub id: Uuid,
+    pub name: String,
+    pub description: String,
+    pub path: PathBuf,
+    pub config: ProjectConfig,
+    pub created_at: SystemTime,
+}
+
+/// 项目管理器的持久化状态
+#[derive(Debug, Serialize, Deserialize)]
+struct ProjectManagerState {
+    projects: Vec<ProjectSnapshot>,
+    recent_projects: Vec<Uuid>,
+}
+
+/// 语言统计信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LanguageStats {
+    pub file_count: usize,
+    pub line_count: usize,
+    pub byte_count: u64,
+    pub line_breakdown: LineBreakdown,
+}
+
+impl LanguageStats {
+    /// 累加另一份统计
+    pub fn merge(&mut self, other: &LanguageStats) {
+        self.file_count += other.file_count;
+        self.line_count += other.line_count;
+        self.byte_count += other.byte_count;
+        self.line_breakdown += other.line_breakdown;
+    }
+    
+    /// 计入单个文件
+    fn record(&mut self, file: &FileInfo) {
+        self.file_count += 1;
+        self.line_count += file.lines;
+        self.byte_count += file.size;
+        self.line_breakdown += file.line_breakdown;
+    }
+}
+
+impl std::ops::AddAssign<&LanguageStats> for LanguageStats {
+    fn add_assign(&mut self, other: &LanguageStats) {
+        self.merge(other);
+    }
+}
+
+impl std::ops::AddAssign for LanguageStats {
+    fn add_assign(&mut self, other: LanguageStats) {
+        self.merge(&other);
+    }
+}
+
+/// 项目统计信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectStatistics {
+    pub total_files: usize,
+    pub total_lines: usize,
+    pub total_size: u64,
+    pub language_stats: HashMap<Language, LanguageStats>,
+    #[serde(with = "unix_millis")]
+    pub created_at: SystemTime,
+    #[serde(with = "unix_millis")]
+    pub updated_at: SystemTime,
+}
+
+impl ProjectStatistics {
+    /// 合并另一份项目统计: 累加总数与各语言统计，
+    /// 创建时间取较早者，更新时间取较晚者
+    pub fn merge(&mut self, other: &ProjectStatistics) {
+        self.total_files += other.total_files;
+        self.total_lines += other.total_lines;
+        self.total_size += other.total_size;
+        
+        for (language, stats) in &other.language_stats {
+            *self.language_stats.entry(*language).or_default() += stats;
+        }
+        
+        self.created_at = self.created_at.min(other.created_at);
+        self.updated_at = self.updated_at.max(other.updated_at);
+    }
+    
+    /// 按行数降序排列的语言统计，行数相同时按语言名排序
+    pub fn sorted_by_lines(&self) -> Vec<(Language, LanguageStats)> {
+        let mut stats = self.sorted_by_name();
+        stats.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.line_count));
+        stats
+    }
+    
+    /// 按语言名排序的语言统计
+    pub fn sorted_by_name(&self) -> Vec<(Language, LanguageStats)> {
+        let mut stats: Vec<_> = self.language_stats
+            .iter()
+            .map(|(language, stats)| (*language, stats.clone()))
+            .collect();
+        stats.sort_by_key(|(language, _)| language.to_string());
+        stats
+    }
+    
+    /// 导出为 JSON，时间字段为 Unix 毫秒时间戳
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+    
+    /// 导出为 CSV，每种语言一行 (按语言名排序)，末尾附合计行
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("language,files,lines,bytes,code,comment,blank\n");
+        let mut total_breakdown = LineBreakdown::default();
+        
+        for (language, stats) in self.sorted_by_name() {
+            let breakdown = stats.line_breakdown;
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                language, stats.file_count, stats.line_count, stats.byte_count,
+                breakdown.code, breakdown.comment, breakdown.blank
+            ));
+            total_breakdown += breakdown;
+        }
+        csv.push_str(&format!(
+            "total,{},{},{},{},{},{}\n",
+            self.total_files, self.total_lines, self.total_size,
+            total_breakdown.code, total_breakdown.comment, total_breakdown.blank
+        ));
+        csv
+    }
+}
+
+/// `SystemTime` 与 Unix 毫秒时间戳之间的 serde 转换
+mod unix_millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let millis = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(serde::ser::Error::custom)?
+            .as_millis();
+        serializer.serialize_u64(millis as u64)
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<SystemTime, D::Error> {
+        let millis = u64::deserialize(deserializer)?;
+        Ok(UNIX_EPOCH + Duration::from_millis(millis))
+    }
+}
+
+/// `Duration` 与秒数之间的 serde 转换
+mod duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+    
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_secs())
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Duration, D::Error> {
+        Ok(Duration::from_secs(u64::deserialize(deserializer)?))
+    }
+}
+
+/// 流式搜索通道的容量，接收端处理不及时时搜索线程会等待
+const SEARCH_CHANNEL_CAPACITY: usize = 256;
+
+/// 最近项目列表默认保留的项目数
+const DEFAULT_RECENT_CAPACITY: usize = 10;
+
+/// 项目管理器
+pub struct ProjectManager {
+    projects: Arc<RwLock<HashMap<Uuid, Arc<Project>>>>,
+    recent_projects: Arc<Mutex<Vec<Uuid>>>,
+    recent_capacity: usize,
+    /// 加载时未能恢复的项目及原因
+    missing_projects: Arc<Mutex<Vec<(ProjectSnapshot, String)>>>,
+}
+
+impl ProjectManager {
+    /// 创建新的项目管理器
+    pub fn new() -> Self {
+        Self::with_recent_capacity(DEFAULT_RECENT_CAPACITY)
+    }
+    
+    /// 创建项目管理器，最近项目列表最多保留 `capacity` 个项目
+    pub fn with_recent_capacity(capacity: usize) -> Self {
+        Self {
+            projects: Arc::new(RwLock::new(HashMap::new())),
+            recent_projects: Arc::new(Mutex::new(Vec::new())),
+            recent_capacity: capacity,
+            missing_projects: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+    
+    /// 将项目列表与最近项目保存为 JSON 文件
+    ///
+    /// 只保存项目元数据与配置，文件索引在加载后重新扫描即可恢复。
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let state = ProjectManagerState {
+            projects: self.get_all_projects()
+                .iter()
+                .map(|project| project.snapshot())
+                .collect(),
+            recent_projects: self.recent_projects.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        };
+        
+        let json = serde_json::to_string_pretty(&state)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+    
+    /// 从 JSON 文件加载项目管理器
+    ///
+    /// 路径已不存在或配置无效的项目不会被加载，可通过 `missing_projects` 获取，其余项目照常加载。
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::load_from_with_themes(path, &ThemeRegistry::builtin())
+    }
+    
+    /// 从 JSON 文件加载项目管理器，并校验配置引用的主题都已在 `themes` 中注册
+    ///
+    /// 引用了未注册主题的项目与路径不存在的项目一样记入 `missing_projects`，不影响其他项目。
+    pub fn load_from_with_themes<P: AsRef<Path>>(path: P, themes: &ThemeRegistry) -> Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let state: ProjectManagerState = serde_json::from_str(&json)?;
+        
+        let manager = Self::new();
+        
+        {
+            let mut projects = manager.projects.write().unwrap_or_else(|e| e.into_inner());
+            let mut missing = manager.missing_projects.lock().unwrap_or_else(|e| e.into_inner());
+            
+            for snapshot in state.projects {
+                if !snapshot.path.is_dir() {
+                    log::warn!("项目路径已不存在: {} ({:?})", snapshot.name, snapshot.path);
+                    let reason = format!("项目路径已不存在: {:?}", snapshot.path);
+                    missing.push((snapshot, reason));
+                    continue;
+                }
+                
+                let restored = themes.validate(&snapshot.config)
+                    .and_then(|_| Project::from_snapshot(snapshot.clone()));
+                match restored {
+                    Ok(project) => {
+                        projects.insert(project.id, Arc::new(project));
+                    }
+                    Err(e) => {
+                        log::warn!("无法恢复项目 {}: {}", snapshot.name, e);
+                        missing.push((snapshot, e.to_string()));
+                    }
+                }
+            }
+        }
+        
+        manager.set_recent(state.recent_projects);
+        Ok(manager)
+    }
+    
+    /// 获取上次加载时未能恢复的项目 (路径不存在或配置无效) 及原因
+    pub fn missing_projects(&self) -> Vec<(ProjectSnapshot, String)> {
+        self.missing_projects.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+    
+    /// 创建项目，项目根目录下有 `.chango.toml` 时使用其中的配置
+    pub fn create_project<S: Into<String>, P: AsRef<Path>>(
+        &self,
+        name: S,
+        description: S,
+        path: P,
+    ) -> Result<Arc<Project>> {
+        let config = ProjectConfig::from_project_dir(&path)?.unwrap_or_default();
+        self.create_project_with_config(name, description, path, config)
+    }
+    
+    /// 使用自定义配置创建项目
+    pub fn create_project_with_config<S: Into<String>, P: AsRef<Path>>(
+        &self,
+        name: S,
+        description: S,
+        path: P,
+        config: ProjectConfig,
+    ) -> Result<Arc<Project>> {
+        let project = Arc::new(Project::with_config(name, description, path, config)?);
+        let project_id = project.id;
+        
+        {
+            let mut projects = self.projects.write().unwrap_or_else(|e| e.into_inner());
+            projects.insert(project_id, project.clone());
+        }
+        
+        self.add_to_recent(project_id);
+        
+        log::info!("创建项目: {} (ID: {})", project.name, project.id);
+        Ok(project)
+    }
+    
+    /// 按模板创建项目
+    ///
+    /// 目录不存在时先创建，写入模板中的目录与文件后创建项目并扫描。
+    /// 项目配置优先使用项目根目录下的 `.chango.toml`。
+    pub fn create_from_template<S: Into<String>, P: AsRef<Path>>(
+        &self,
+        name: S,
+        path: P,
+        template: ProjectTemplate,
+    ) -> Result<Arc<Project>> {
+        let path = path.as_ref();
+        fs::create_dir_all(path)?;
+        template.apply(path)?;
+        
+        let config = ProjectConfig::from_project_dir(path)?.unwrap_or_default();
+        let project = self.create_project_with_config(name.into(), String::new(), path, config)?;
+        let report = project.scan_files()?;
+        log::info!("按模板 {} 创建项目，索引 {} 个文件", template.name, report.indexed);
+        Ok(project)
+    }
+    
+    /// 获取项目
+    pub fn get_project(&self, id: &Uuid) -> Option<Arc<Project>> {
+        self.projects.read().unwrap_or_else(|e| e.into_inner()).get(id).cloned()
+    }
+    
+    /// 获取所有项目
+    pub fn get_all_projects(&self) -> Vec<Arc<Project>> {
+        self.projects.read().unwrap_or_else(|e| e.into_inner()).values().cloned().collect()
+    }
+    
+    /// 汇总所有项目的统计信息
+    ///
+    /// 先逐个项目收集统计再合并，不会同时持有多个项目的锁。没有项目时返回空统计。
+    pub fn aggregate_statistics(&self) -> ProjectStatistics {
+        let mut stats = self.get_all_projects()
+            .iter()
+            .map(|project| project.get_statistics())
+            .collect::<Vec<_>>()
+            .into_iter();
+        
+        match stats.next() {
+            Some(mut total) => {
+                for project_stats in stats {
+                    total.merge(&project_stats);
+                }
+                total
+            }
+            None => {
+                let now = SystemTime::now();
+                ProjectStatistics {
+                    total_files: 0,
+                    total_lines: 0,
+                    total_size: 0,
+                    language_stats: HashMap::new(),
+                    created_at: now,
+                    updated_at: now,
+                }
+            }
+        }
+    }
+    
+    /// 删除项目
+    pub fn remove_project(&self, id: &Uuid) -> Result<()> {
+        // 先释放项目表的写锁再更新 recent_projects，与 get_recent_projects 的加锁顺序不冲突
+        let removed = self.projects.write().unwrap_or_else(|e| e.into_inner()).remove(id);
+        if removed.is_none() {
+            return Err(ChangoEditorError::ProjectNotFound(*id));
+        }
+        
+        self.remove_from_recent(id);
+        log::info!("删除项目: {}", id);
+        Ok(())
+    }
+    
+    /// 最近项目的 ID，最近使用的在前，用于跨会话保存
+    pub fn recent_project_ids(&self) -> Vec<Uuid> {
+        self.recent_projects.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+    
+    /// 恢复之前保存的最近项目列表 (最近使用的在前)
+    ///
+    /// 重复的 ID 只保留第一次出现，未注册的项目被忽略，超出容量的部分被截断，
+    /// 因此应在项目加载完成后调用。
+    pub fn set_recent(&self, ids: Vec<Uuid>) {
+        // 先释放项目表的读锁再获取 recent_projects，与 get_recent_projects 的加锁顺序不冲突
+        let restored: Vec<Uuid> = {
+            let projects = self.projects.read().unwrap_or_else(|e| e.into_inner());
+            let mut seen = HashSet::new();
+            ids.into_iter()
+                .filter(|id| projects.contains_key(id) && seen.insert(*id))
+                .take(self.recent_capacity)
+                .collect()
+        };
+        
+        *self.recent_projects.lock().unwrap_or_else(|e| e.into_inner()) = restored;
+    }
+    
+    /// 获取最近项目，最多 `limit` 个
+    pub fn get_recent_projects(&self, limit: usize) -> Vec<Arc<Project>> {
+        let recent = self.recent_projects.lock().unwrap_or_else(|e| e.into_inner());
+        let projects = self.projects.read().unwrap_or_else(|e| e.into_inner());
+        
+        recent
+            .iter()
+            .take(limit)
+            .filter_map(|id| projects.get(id).cloned())
+            .collect()
+    }
+    
+    /// 添加到最近项目
+    fn add_to_recent(&self, project_id: Uuid) {
+        let mut recent = self.recent_projects.lock().unwrap_or_else(|e| e.into_inner());
+        
+        // 移除已存在的条目
+        recent.retain(|&id| id != project_id);
+        
+        // 添加到开头
+        recent.insert(0, project_id);
+        
+        // 限制最大数量
+        recent.truncate(self.recent_capacity);
+    }
+    
+    /// 从最近项目中移除
+    fn remove_from_recent(&self, project_id: &Uuid) {
+        let mut recent = self.recent_projects.lock().unwrap_or_else(|e| e.into_inner());
+        recent.retain(|id| id != project_id);
+    }
+}
+
+impl Default for ProjectManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 词法单元类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TokenKind {
+    Keyword,
+    Comment,
+    String,
+    Number,
+    Identifier,
+    Punctuation,
+    Whitespace,
+}
+
+/// 词法单元，`start..end` 为源码中的字节偏移，均位于字符边界上
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Token {
+    /// 取出词法单元对应的源码片段
+    pub fn text<'a>(&self, code: &'a str) -> &'a str {
+        &code[self.start..self.end]
+    }
+}
+
+/// 语法高亮器特征
+pub trait SyntaxHighlighter {
+    fn highlight(&self, code: &str, language: Language) -> Result<String>;
+    fn tokenize(&self, code: &str, language: Language) -> Result<Vec<Token>>;
+    fn get_keywords(&self, language: Language) -> &[&str];
+}
+
+/// 所有高亮器实例共享的关键字正则，首次创建高亮器时编译
+static KEYWORD_PATTERNS: once_cell::sync::OnceCell<HashMap<Language, regex::Regex>> =
+    once_cell::sync::OnceCell::new();
+
+/// 为每个有关键字的语言编译关键字正则
+fn build_keyword_patterns() -> Result<HashMap<Language, regex::Regex>> {
+    let mut keyword_patterns = HashMap::new();
+    
+    for &language in Language::ALL {
+        let keywords = language.keywords();
+        if keywords.is_empty() {
+            continue;
+        }
+        
+        // 词法分析已按语言的标识符字符切分，这里只需整词匹配
+        let escaped: Vec<String> = keywords.iter().map(|k| regex::escape(k)).collect();
+        let flags = if language.is_case_sensitive() { "" } else { "(?i)" };
+        let pattern = format!(r"{}^(?:{})$", flags, escaped.join("|"));
+        let regex = regex::Regex::new(&pattern)?;
+        keyword_patterns.insert(language, regex);
+    }
+    
+    Ok(keyword_patterns)
+}
+
+/// RGB 颜色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+    
+    /// `#rrggbb` 形式的十六进制表示
+    pub fn to_hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// 词法单元的显示样式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Style {
+    pub color: Color,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+impl Style {
+    pub const fn new(color: Color) -> Self {
+        Self { color, bold: false, italic: false }
+    }
+    
+    pub const fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+    
+    pub const fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+}
+
+/// 高亮主题: 各类词法单元的样式，未设置样式的单元按原样输出
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    pub styles: HashMap<TokenKind, Style>,
+}
+
+impl Theme {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            styles: HashMap::new(),
+        }
+    }
+    
+    /// 设置某类词法单元的样式
+    pub fn with_style(mut self, kind: TokenKind, style: Style) -> Self {
+        self.styles.insert(kind, style);
+        self
+    }
+    
+    pub fn style(&self, kind: TokenKind) -> Option<&Style> {
+        self.styles.get(&kind)
+    }
+    
+    /// 内置暗色主题，各语言主题只有关键字颜色不同
+    fn dark<S: Into<String>>(name: S, keyword: Color) -> Self {
+        Self::new(name)
+            .with_style(TokenKind::Keyword, Style::new(keyword).bold())
+            .with_style(TokenKind::Comment, Style::new(Color::rgb(0x6a, 0x99, 0x55)).italic())
+            .with_style(TokenKind::String, Style::new(Color::rgb(0xce, 0x91, 0x78)))
+            .with_style(TokenKind::Number, Style::new(Color::rgb(0xb5, 0xce, 0xa8)))
+    }
+}
+
+/// 主题注册表
+#[derive(Debug, Clone, Default)]
+pub struct ThemeRegistry {
+    themes: HashMap<String, Theme>,
+}
+
+impl ThemeRegistry {
+    /// 创建空注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 包含默认配置所引用主题的注册表
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(Theme::dark("rust-dark", Color::rgb(0xf7, 0x8c, 0x6c)));
+        registry.register(Theme::dark("python-dark", Color::rgb(0x56, 0x9c, 0xd6)));
+        registry.register(Theme::dark("js-dark", Color::rgb(0xdc, 0xdc, 0xaa)));
+        registry
+    }
+    
+    /// 注册主题，同名主题会被替换
+    pub fn register(&mut self, theme: Theme) {
+        self.themes.insert(theme.name.clone(), theme);
+    }
+    
+    pub fn get(&self, name: &str) -> Option<&Theme> {
+        self.themes.get(name)
+    }
+    
+    /// 校验配置中引用的主题都已注册
+    pub fn validate(&self, config: &ProjectConfig) -> Result<()> {
+        for (language, name) in &config.syntax_themes {
+            if !self.themes.contains_key(name) {
+                return Err(ChangoEditorError::ValidationError(
+                    format!("{} 使用的主题不存在: {}", language, name),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `SimpleSyntaxHighlighter::highlight` 为各类词法单元插入的标记
+///
+/// 默认只用 `<keyword>`/`</keyword>` 包裹关键字并对源码做 HTML 转义。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighlightFormat {
+    wrappers: HashMap<TokenKind, (String, String)>,
+    escape_html: bool,
+}
+
+impl HighlightFormat {
+    /// 不包裹任何词法单元的空格式
+    pub fn new() -> Self {
+        Self {
+            wrappers: HashMap::new(),
+            escape_html: true,
+        }
+    }
+    
+    /// 用 `<span class="...">` 包裹各类词法单元的 HTML 格式
+    pub fn html_classes() -> Self {
+        let span = |class: &str| (format!("<span class=\"{}\">", class), "</span>".to_string());
+        let mut format = Self::new();
+        for (kind, class) in [
+            (TokenKind::Keyword, "kw"),
+            (TokenKind::Comment, "cm"),
+            (TokenKind::String, "st"),
+            (TokenKind::Number, "nu"),
+        ] {
+            let (open, close) = span(class);
+            format = format.wrap(kind, open, close);
+        }
+        format
+    }
+    
+    /// 设置某类词法单元的前后标记
+    pub fn wrap<O: Into<String>, C: Into<String>>(mut self, kind: TokenKind, open: O, close: C) -> Self {
+        self.wrappers.insert(kind, (open.into(), close.into()));
+        self
+    }
+    
+    /// 是否对源码文本做 HTML 转义 (输出 Markdown 等非 HTML 格式时关闭)
+    pub fn escape_html(mut self, enabled: bool) -> Self {
+        self.escape_html = enabled;
+        self
+    }
+    
+    /// 某类词法单元的前后标记
+    pub fn markers(&self, kind: TokenKind) -> Option<(&str, &str)> {
+        self.wrappers.get(&kind).map(|(open, close)| (open.as_str(), close.as_str()))
+    }
+}
+
+impl Default for HighlightFormat {
+    fn default() -> Self {
+        Self::new().wrap(TokenKind::Keyword, "<keyword>", "</keyword>")
+    }
+}
+
+/// 简单语法高亮器实现
+pub struct SimpleSyntaxHighlighter {
+    keyword_patterns: &'static HashMap<Language, regex::Regex>,
+    format: HighlightFormat,
+}
+
+impl SimpleSyntaxHighlighter {
+    pub fn new() -> Result<Self> {
+        let keyword_patterns = KEYWORD_PATTERNS.get_or_try_init(build_keyword_patterns)?;
+        
+        Ok(Self {
+            keyword_patterns,
+            format: HighlightFormat::default(),
+        })
+    }
+    
+    /// 使用自定义的高亮标记
+    pub fn with_format(mut self, format: HighlightFormat) -> Self {
+        self.format = format;
+        self
+    }
+    
+    /// 按主题输出带内联样式的 HTML，没有样式的词法单元只做转义
+    pub fn highlight_with_theme(&self, code: &str, language: Language, theme: &Theme) -> Result<String> {
+        let mut highlighted = String::with_capacity(code.len());
+        
+        for token in self.tokenize(code, language)? {
+            let text = escape_html(token.text(code));
+            match theme.style(token.kind) {
+                Some(style) => {
+                    let mut css = format!("color:{}", style.color.to_hex());
+                    if style.bold {
+                        css.push_str(";font-weight:bold");
+                    }
+                    if style.italic {
+                        css.push_str(";font-style:italic");
+                    }
+                    highlighted.push_str(&format!("<span style=\"{}\">{}</span>", css, text));
+                }
+                None => highlighted.push_str(&text),
+            }
+        }
+        
+        Ok(highlighted)
+    }
+    
+    /// 判断标识符是否为该语言的关键字
+    fn is_keyword(&self, word: &str, language: Language) -> bool {
+        self.keyword_patterns
+            .get(&language)
+            .is_some_and(|regex| regex.is_match(word))
+    }
+}
+
+impl SyntaxHighlighter for SimpleSyntaxHighlighter {
+    fn highlight(&self, code: &str, language: Language) -> Result<String> {
+        // 源码文本逐个词法单元转义，插入的标签本身不转义
+        let mut highlighted = String::with_capacity(code.len());
+        
+        for token in self.tokenize(code, language)? {
+            let text = if self.format.escape_html {
+                escape_html(token.text(code))
+            } else {
+                token.text(code).to_string()
+            };
+            
+            match self.format.markers(token.kind) {
+                Some((open, close)) => {
+                    highlighted.push_str(open);
+                    highlighted.push_str(&text);
+                    highlighted.push_str(close);
+                }
+                None => highlighted.push_str(&text),
+            }
+        }
+        
+        Ok(highlighted)
+    }
+    
+    fn tokenize(&self, code: &str, language: Language) -> Result<Vec<Token>> {
+        let line_comment = language.line_comment();
+        let block_comment = language.block_comment();
+        let mut tokens = Vec::new();
+        let mut pos = 0;
+        
+        while let Some(c) = code[pos..].chars().next() {
+            let rest = &code[pos..];
+            
+            let (kind, len) = if c.is_whitespace() {
+                (TokenKind::Whitespace, prefix_len(rest, char::is_whitespace))
+            } else if line_comment.is_some_and(|marker| rest.starts_with(marker)) {
+                (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+            } else if let Some((open, close)) = block_comment.filter(|(open, _)| rest.starts_with(open)) {
+                let len = rest[open.len()..]
+                    .find(close)
+                    .map_or(rest.len(), |i| open.len() + i + close.len());
+                (TokenKind::Comment, len)
+            } else if let Some(len) = string_literal_len(rest, language) {
+                (TokenKind::String, len)
+            } else if c.is_ascii_digit() {
+                (TokenKind::Number, prefix_len(rest, |c| c.is_alphanumeric() || c == '_' || c == '.'))
+            } else if !c.is_numeric() && language.is_identifier_char(c) {
+                let len = prefix_len(rest, |c| language.is_identifier_char(c));
+                if self.is_keyword(&rest[..len], language) {
+                    (TokenKind::Keyword, len)
+                } else {
+                    (TokenKind::Identifier, len)
+                }
+            } else {
+                (TokenKind::Punctuation, c.len_utf8())
+            };
+            
+            tokens.push(Token { kind, start: pos, end: pos + len });
+            pos += len;
+        }
+        
+        Ok(tokens)
+    }
+    
+    fn get_keywords(&self, language: Language) -> &[&str] {
+        language.keywords()
+    }
+}
+
+/// 终端高亮器: 按主题颜色输出 ANSI SGR 转义序列
+///
+/// 每个词法单元结束以及跨行单元的每个行尾都会重置样式。
+/// 设置了非空的 `NO_COLOR` 环境变量时默认不输出颜色。
+pub struct AnsiHighlighter {
+    inner: SimpleSyntaxHighlighter,
+    theme: Theme,
+    color: bool,
+}
+
+impl AnsiHighlighter {
+    pub fn new(theme: Theme) -> Result<Self> {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        
+        Ok(Self {
+            inner: SimpleSyntaxHighlighter::new()?,
+            theme,
+            color: !no_color,
+        })
+    }
+    
+    /// 显式开启或关闭颜色输出，覆盖 `NO_COLOR` 的判断
+    pub fn with_color(mut self, enabled: bool) -> Self {
+        self.color = enabled;
+        self
+    }
+    
+    /// 样式对应的 SGR 参数
+    fn sgr(style: &Style) -> String {
+        let mut codes = Vec::new();
+        if style.bold {
+            codes.push("1".to_string());
+        }
+        if style.italic {
+            codes.push("3".to_string());
+        }
+        codes.push(format!("38;2;{};{};{}", style.color.r, style.color.g, style.color.b));
+        codes.join(";")
+    }
+}
+
+impl SyntaxHighlighter for AnsiHighlighter {
+    fn highlight(&self, code: &str, language: Language) -> Result<String> {
+        if !self.color {
+            return Ok(code.to_string());
+        }
+        
+        let mut highlighted = String::with_capacity(code.len());
+        
+        for token in self.tokenize(code, language)? {
+            let text = token.text(code);
+            let style = match self.theme.style(token.kind) {
+                Some(style) => style,
+                None => {
+                    highlighted.push_str(text);
+                    continue;
+                }
+            };
+            
+            let sgr = Self::sgr(style);
+            for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    highlighted.push('\n');
+                }
+                if !line.is_empty() {
+                    highlighted.push_str(&format!("\x1b[{}m{}\x1b[0m", sgr, line));
+                }
+            }
+        }
+        
+        Ok(highlighted)
+    }
+    
+    fn tokenize(&self, code: &str, language: Language) -> Result<Vec<Token>> {
+        self.inner.tokenize(code, language)
+    }
+    
+    fn get_keywords(&self, language: Language) -> &[&str] {
+        self.inner.get_keywords(language)
+    }
+}
+
+/// 带 LRU 缓存的高亮器包装
+///
+/// 以 (内容哈希, 语言) 为键缓存 `highlight` 的结果，命中时不再运行词法分析。
+/// 内容被编辑后哈希随之变化，自然不会命中旧结果。可在多个线程间共享。
+pub struct CachingHighlighter<H> {
+    inner: H,
+    capacity: usize,
+    cache: Mutex<HighlightCache>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Default)]
+struct HighlightCache {
+    entries: HashMap<(u128, Language), (String, u64)>,
+    /// 最近使用时刻 -> 键，最小的时刻最久未使用
+    recency: std::collections::BTreeMap<u64, (u128, Language)>,
+    clock: u64,
+}
+
+impl<H: SyntaxHighlighter> CachingHighlighter<H> {
+    /// `capacity` 为最多缓存的结果数，为 0 时不缓存
+    pub fn new(inner: H, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity,
+            cache: Mutex::new(HighlightCache::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+    
+    /// 缓存命中次数
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+    
+    /// 缓存未命中次数
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+    
+    /// 当前缓存的结果数
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).entries.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// 清空缓存，计数器保持不变
+    pub fn clear(&self) {
+        *self.cache.lock().unwrap_or_else(|e| e.into_inner()) = HighlightCache::default();
+    }
+}
+
+impl<H: SyntaxHighlighter> SyntaxHighlighter for CachingHighlighter<H> {
+    fn highlight(&self, code: &str, language: Language) -> Result<String> {
+        let key = (xxhash_rust::xxh3::xxh3_128(code.as_bytes()), language);
+        
+        {
+            let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+            let cache = &mut *cache;
+            cache.clock += 1;
+            if let Some((highlighted, used_at)) = cache.entries.get_mut(&key) {
+                cache.recency.remove(used_at);
+                *used_at = cache.clock;
+                cache.recency.insert(cache.clock, key);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(highlighted.clone());
+            }
+        }
+        
+        // 高亮在锁外进行，并发的相同请求可能各自计算一次
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let highlighted = self.inner.highlight(code, language)?;
+        if self.capacity == 0 {
+            return Ok(highlighted);
+        }
+        
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        let cache = &mut *cache;
+        cache.clock += 1;
+        if let Some((_, used_at)) = cache.entries.insert(key, (highlighted.clone(), cache.clock)) {
+            cache.recency.remove(&used_at);
+        }
+        cache.recency.insert(cache.clock, key);
+        
+        while cache.entries.len() > self.capacity {
+            match cache.recency.pop_first() {
+                Some((_, oldest)) => {
+                    cache.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        
+        Ok(highlighted)
+    }
+    
+    fn tokenize(&self, code: &str, language: Language) -> Result<Vec<Token>> {
+        self.inner.tokenize(code, language)
+    }
+    
+    fn get_keywords(&self, language: Language) -> &[&str] {
+        self.inner.get_keywords(language)
+    }
+}
+
+/// 满足条件的最长前缀的字节长度
+fn prefix_len(text: &str, predicate: impl Fn(char) -> bool) -> usize {
+    text.char_indices()
+        .find(|&(_, c)| !predicate(c))
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// 若文本以字符串字面量开头，返回其字节长度
+///
+/// 普通引号字符串止于同一行内未转义的闭合引号，未闭合时止于行尾；
+/// Python 三引号字符串与 JavaScript/TypeScript 模板字符串可以跨行。
+/// Rust 中不构成字符字面量的单引号是生命周期标记，不视为字符串。
+fn string_literal_len(text: &str, language: Language) -> Option<usize> {
+    let quote = text.chars().next()?;
+    let multiline = match quote {
+        '"' | '\'' => false,
+        '`' if matches!(language, Language::JavaScript | Language::TypeScript) => true,
+        _ => return None,
+    };
+    
+    if language == Language::Python {
+        let triple = if quote == '"' { "\"\"\"" } else { "\'\'\'" };
+        if text.starts_with(triple) {
+            return Some(text[3..].find(triple).map_or(text.len(), |i| 3 + i + 3));
+        }
+    }
+    
+    if language == Language::Rust && quote == '\'' {
+        let mut chars = text[1..].chars();
+        match (chars.next(), chars.next()) {
+            (Some('\\'), _) => {}
+            (Some(c), Some('\'')) => return Some(1 + c.len_utf8() + 1),
+            _ => return None,
+        }
+    }
+    
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return Some(i + c.len_utf8());
+        } else if c == '\n' && !multiline {
+            return Some(i);
+        }
+    }
+    Some(text.len())
+}
+
+/// 转义 HTML 特殊字符
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 单次文本编辑，偏移量为字节偏移
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    Insert { offset: usize, text: String },
+    Delete { offset: usize, text: String },
+}
+
+impl Edit {
+    fn apply(&self, buffer: &mut String) {
+        match self {
+            Edit::Insert { offset, text } => buffer.insert_str(*offset, text),
+            Edit::Delete { offset, text } => {
+                buffer.replace_range(*offset..*offset + text.len(), "");
+            }
+        }
+    }
+    
+    fn inverse(&self) -> Edit {
+        match self {
+            Edit::Insert { offset, text } => Edit::Delete { offset: *offset, text: text.clone() },
+            Edit::Delete { offset, text } => Edit::Insert { offset: *offset, text: text.clone() },
+        }
+    }
+}
+
+/// 编辑历史: 记录对缓冲区的编辑并支持撤销/重做
+///
+/// 紧邻的连续单字符输入合并为一个撤销步骤，换行或任何其他操作会结束合并。
+/// 超过最大深度时丢弃最早的步骤。
+#[derive(Debug, Clone)]
+pub struct EditHistory {
+    undo_stack: std::collections::VecDeque<Edit>,
+    redo_stack: Vec<Edit>,
+    max_depth: usize,
+    coalescing: bool,
+}
+
+impl EditHistory {
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            undo_stack: std::collections::VecDeque::new(),
+            redo_stack: Vec::new(),
+            max_depth,
+            coalescing: false,
+        }
+    }
+    
+    /// 使用项目配置中的撤销深度
+    pub fn from_config(config: &ProjectConfig) -> Self {
+        Self::new(config.max_undo_depth)
+    }
+    
+    /// 在 `offset` 处插入文本并记录
+    pub fn insert(&mut self, buffer: &mut String, offset: usize, text: &str) -> Result<()> {
+        check_char_boundary(buffer, offset)?;
+        if text.is_empty() {
+            return Ok(());
+        }
+        
+        buffer.insert_str(offset, text);
+        
+        let single_char = text.chars().count() == 1;
+        if self.coalescing && single_char {
+            if let Some(Edit::Insert { offset: last_offset, text: last_text }) = self.undo_stack.back_mut() {
+                if *last_offset + last_text.len() == offset {
+                    last_text.push_str(text);
+                    self.redo_stack.clear();
+                    self.coalescing = text != "\n";
+                    return Ok(());
+                }
+            }
+        }
+        
+        self.record(Edit::Insert { offset, text: text.to_string() });
+        self.coalescing = single_char && text != "\n";
+        Ok(())
+    }
+    
+    /// 删除 `range` 范围内的文本并记录
+    pub fn delete(&mut self, buffer: &mut String, range: std::ops::Range<usize>) -> Result<()> {
+        check_char_boundary(buffer, range.start)?;
+        check_char_boundary(buffer, range.end)?;
+        if range.start >= range.end {
+            return Ok(());
+        }
+        
+        let text = buffer[range.clone()].to_string();
+        buffer.replace_range(range.clone(), "");
+        self.record(Edit::Delete { offset: range.start, text });
+        self.coalescing = false;
+        Ok(())
+    }
+    
+    /// 撤销最近一步，没有可撤销的步骤时返回 false
+    pub fn undo(&mut self, buffer: &mut String) -> bool {
+        self.coalescing = false;
+        match self.undo_stack.pop_back() {
+            Some(edit) => {
+                edit.inverse().apply(buffer);
+                self.redo_stack.push(edit);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// 重做最近撤销的一步，没有可重做的步骤时返回 false
+    pub fn redo(&mut self, buffer: &mut String) -> bool {
+        self.coalescing = false;
+        match self.redo_stack.pop() {
+            Some(edit) => {
+                edit.apply(buffer);
+                self.undo_stack.push_back(edit);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+    
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+    
+    /// 清空全部历史
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.coalescing = false;
+    }
+    
+    /// 记录新的编辑，新编辑会使重做栈失效
+    fn record(&mut self, edit: Edit) {
+        self.redo_stack.clear();
+        self.undo_stack.push_back(edit);
+        while self.undo_stack.len() > self.max_depth {
+            self.undo_stack.pop_front();
+        }
+    }
+}
+
+/// 基于 rope 的文本缓冲区，插入与删除的代价与文件大小无关
+///
+/// 偏移量均为字节偏移，必须位于字符边界上。
+#[derive(Debug, Clone, Default)]
+pub struct TextBuffer {
+    rope: ropey::Rope,
+}
+
+impl TextBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 从文件读取内容 (必须是 UTF-8)
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = fs::File::open(path)?;
+        let rope = ropey::Rope::from_reader(BufReader::with_capacity(READ_BUFFER_SIZE, file))?;
+        Ok(Self { rope })
+    }
+    
+    /// 在 `offset` 处插入文本
+    pub fn insert(&mut self, offset: usize, text: &str) -> Result<()> {
+        let char_index = self.char_index(offset)?;
+        self.rope.insert(char_index, text);
+        Ok(())
+    }
+    
+    /// 删除 `range` 范围内的文本
+    pub fn delete(&mut self, range: std::ops::Range<usize>) -> Result<()> {
+        let start = self.char_index(range.start)?;
+        let end = self.char_index(range.end)?;
+        if start < end {
+            self.rope.remove(start..end);
+        }
+        Ok(())
+    }
+    
+    /// 第 `n` 行 (从 0 开始) 的内容，包含行尾换行符；内容连续时不复制
+    pub fn line(&self, n: usize) -> Option<std::borrow::Cow<'_, str>> {
+        (n < self.rope.len_lines()).then(|| self.rope.line(n).into())
+    }
+    
+    /// rope 意义上的行数: 空缓冲区为 1，以换行结尾时最后一个空行也计入
+    ///
+    /// 除 `\n` 外，单独的 `\r`、U+2028 等 Unicode 换行符也会分行。
+    pub fn len_lines(&self) -> usize {
+        self.rope.len_lines()
+    }
+    
+    /// 与 `FileInfo::lines` 口径一致的行数: 只按 `\n` 分行，需要遍历全部内容
+    pub fn line_count(&self) -> usize {
+        let mut counter = LineCounter::default();
+        for chunk in self.rope.chunks() {
+            counter.feed(chunk.as_bytes());
+        }
+        counter.finish()
+    }
+    
+    pub fn len_bytes(&self) -> usize {
+        self.rope.len_bytes()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.rope.len_bytes() == 0
+    }
+    
+    /// 将字节偏移转换为字符下标，越界或不在字符边界上时报错
+    fn char_index(&self, offset: usize) -> Result<usize> {
+        let invalid = || {
+            ChangoEditorError::ValidationError(format!("偏移量 {} 超出范围或不在字符边界上", offset))
+        };
+        
+        let char_index = self.rope.try_byte_to_char(offset).map_err(|_| invalid())?;
+        if self.rope.char_to_byte(char_index) != offset {
+            return Err(invalid());
+        }
+        Ok(char_index)
+    }
+}
+
+impl fmt::Display for TextBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in self.rope.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&str> for TextBuffer {
+    fn from(text: &str) -> Self {
+        Self { rope: ropey::Rope::from_str(text) }
+    }
+}
+
+/// 检查偏移量在缓冲区范围内且位于字符边界上
+fn check_char_boundary(buffer: &str, offset: usize) -> Result<()> {
+    if buffer.is_char_boundary(offset) {
+        Ok(())
+    } else {
+        Err(ChangoEditorError::ValidationError(
+            format!("偏移量 {} 超出范围或不在字符边界上", offset),
+        ))
+    }
+}
+
+/// 文件处理器特征
+pub trait FileProcessor: Send + Sync {
+    fn process(&self, file: &FileInfo) -> Result<()>;
+    fn get_name(&self) -> &str;
+    
+    /// 预览处理结果而不写回文件，返回相对原文件的 unified diff；
+    /// 没有变化或不支持预览时返回 `None`
+    fn process_dry_run(&self, _file: &FileInfo) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// 并发文件处理器
+///
+/// 工作线程在第一次处理时创建，并在处理器的整个生命周期内复用；丢弃处理器时关闭线程池。
+pub struct ConcurrentFileProcessor<T: FileProcessor> {
+    processor: Arc<T>,
+    worker_count: usize,
+    pool: Mutex<Option<WorkerPool>>,
+}
+
+impl<T: FileProcessor + 'static> ConcurrentFileProcessor<T> {
+    pub fn new(processor: T, worker_count: usize) -> Self {
+        Self {
+            processor: Arc::new(processor),
+            worker_count,
+            pool: Mutex::new(None),
+        }
+    }
+    
+    /// 按机器的可用并行度确定工作线程数，限制在 1 到 `MAX_DEFAULT_WORKERS` 之间
+    pub fn with_default_parallelism(processor: T) -> Self {
+        let worker_count = thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .clamp(1, MAX_DEFAULT_WORKERS);
+        Self::new(processor, worker_count)
+    }
+    
+    /// 并发处理文件列表
+    pub fn process_files(&self, files: Vec<FileInfo>) -> Result<Vec<Result<()>>> {
+        self.run_batch(files, BatchOptions::default())
+    }
+    
+    /// 可取消的并发处理
+    ///
+    /// 工作线程在每个文件之间检查取消标志；标志置位后剩余文件不再处理，
+    /// 对应结果为 `Err(ChangoEditorError::Cancelled)`。
+    pub fn process_files_cancellable(
+        &self,
+        files: Vec<FileInfo>,
+        cancel: Arc<AtomicBool>,
+    ) -> Result<Vec<Result<()>>> {
+        self.run_batch(files, BatchOptions { cancel: Some(cancel), ..Default::default() })
+    }
+    
+    /// 带进度回调的并发处理
+    ///
+    /// 每个文件处理完成后以 `(已完成数, 总数)` 调用回调。回调在调用线程中串行执行，
+    /// 因此即使多个工作线程同时完成，计数也严格递增并最终等于总数。
+    pub fn process_files_with_progress<F>(
+        &self,
+        files: Vec<FileInfo>,
+        progress: F,
+    ) -> Result<Vec<Result<()>>>
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        self.run_batch(files, BatchOptions { progress: Some(Arc::new(progress)), ..Default::default() })
+    }
+    
+    /// 带单文件超时的并发处理
+    ///
+    /// 每个文件在独立的子线程中处理，超过 `timeout` 仍未返回时记为
+    /// `ChangoEditorError::Timeout`，工作线程继续处理下一个文件。
+    /// 注意: 线程无法被强制终止，超时的处理仍会在后台运行直到自行结束，
+    /// 其结果被丢弃；永久挂起的处理器会一直占用一个后台线程。
+    pub fn process_files_with_timeout(
+        &self,
+        files: Vec<FileInfo>,
+        timeout: Duration,
+    ) -> Result<Vec<Result<()>>> {
+        self.run_batch(files, BatchOptions { timeout: Some(timeout), ..Default::default() })
+    }
+    
+    fn run_batch(&self, files: Vec<FileInfo>, options: BatchOptions) -> Result<Vec<Result<()>>> {
+        let total = files.len();
+        let jobs = self.pool_sender(total);
+        let (done_tx, done_rx) = crossbeam_channel::unbounded::<(usize, Result<()>)>();
+        
+        // 向线程池派发任务
+        for (index, file) in files.into_iter().enumerate() {
+            let processor = self.processor.clone();
+            let options = options.clone();
+            let done_tx = done_tx.clone();
+            
+            let job: Job = Box::new(move |worker_id| {
+                let cancelled = options.cancel.as_ref()
+                    .is_some_and(|flag| flag.load(Ordering::Relaxed));
+                
+                let result = if cancelled {
+                    Err(ChangoEditorError::Cancelled)
+                } else {
+                    log::debug!("工作线程 {} 处理文件: {}", worker_id, file.name);
+                    match options.timeout {
+                        Some(timeout) => process_with_timeout(&processor, &file, timeout),
+                        None => process_catching_panic(&*processor, &file),
+                    }
+                };
+                
+                let _ = done_tx.send((index, result));
+            });
+            
+            jobs.send(job).map_err(|_| {
+                ChangoEditorError::ProcessorPanic("线程池已关闭".to_string())
+            })?;
+        }
+        drop(done_tx);
+        
+        // 收集结果，所有任务结束 (包括意外退出) 后通道关闭
+        let mut slots: Vec<Option<Result<()>>> = (0..total).map(|_| None).collect();
+        let mut completed = 0;
+        for (index, result) in done_rx {
+            slots[index] = Some(result);
+            completed += 1;
+            if let Some(progress) = &options.progress {
+                progress(completed, total);
+            }
+        }
+        
+        Ok(slots
+            .into_iter()
+            .map(|slot| {
+                slot.unwrap_or_else(|| {
+                    Err(ChangoEditorError::ProcessorPanic("工作线程异常退出，文件未处理".to_string()))
+                })
+            })
+            .collect())
+    }
+    
+    /// 获取线程池的任务发送端，首次调用时创建线程池
+    ///
+    /// 工作线程按需创建，数量不超过 `worker_count`，也不超过本批文件数。
+    fn pool_sender(&self, file_count: usize) -> crossbeam_channel::Sender<Job> {
+        let mut pool = self.pool.lock().unwrap_or_else(|e| e.into_inner());
+        let pool = pool.get_or_insert_with(WorkerPool::new);
+        pool.grow_to(self.worker_count.max(1).min(file_count.max(1)));
+        pool.sender.clone()
+    }
+}
+
+impl<T: FileProcessor> Drop for ConcurrentFileProcessor<T> {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.get_mut().unwrap_or_else(|e| e.into_inner()).take() {
+            pool.shutdown();
+        }
+    }
+}
+
+/// 线程池任务，参数为执行任务的工作线程编号
+type Job = Box<dyn FnOnce(usize) + Send>;
+
+/// 固定大小的工作线程池
+struct WorkerPool {
+    sender: crossbeam_channel::Sender<Job>,
+    receiver: crossbeam_channel::Receiver<Job>,
+    handles: Vec<thread::JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    /// 创建没有工作线程的线程池，线程由 `grow_to` 按需创建
+    fn new() -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded::<Job>();
+        Self { sender, receiver, handles: Vec::new() }
+    }
+    
+    /// 把工作线程增加到 `worker_count` 个，已有线程不会减少
+    fn grow_to(&mut self, worker_count: usize) {
+        while self.handles.len() < worker_count {
+            let worker_id = self.handles.len();
+            let receiver = self.receiver.clone();
+            self.handles.push(thread::spawn(move || {
+                // 任务中的 panic 不应让工作线程退出
+                while let Ok(job) = receiver.recv() {
+                    if let Err(payload) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| job(worker_id))) {
+                        log::error!("工作线程任务异常: {}", panic_message(&*payload));
+                    }
+                }
+            }));
+        }
+    }
+    
+    /// 关闭任务通道并等待所有工作线程结束
+    fn shutdown(self) {
+        drop(self.sender);
+        for handle in self.handles {
+            if let Err(payload) = handle.join() {
+                log::error!("工作线程异常退出: {}", panic_message(&*payload));
+            }
+        }
+    }
+}
+
+/// 调用处理器，单个文件的 panic 只影响该文件的结果，不终止工作线程
+fn process_catching_panic<T: FileProcessor + ?Sized>(processor: &T, file: &FileInfo) -> Result<()> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| processor.process(file)))
+        .unwrap_or_else(|payload| Err(ChangoEditorError::ProcessorPanic(panic_message(&*payload))))
+}
+
+/// 在子线程中处理文件并限制等待时间，超时后子线程在后台继续运行
+fn process_with_timeout<T: FileProcessor + ?Sized + 'static>(
+    processor: &Arc<T>,
+    file: &FileInfo,
+    timeout: Duration,
+) -> Result<()> {
+    let (tx, rx) = crossbeam_channel::bounded(1);
+    let processor = processor.clone();
+    let task_file = file.clone();
+    
+    thread::spawn(move || {
+        let _ = tx.send(process_catching_panic(&*processor, &task_file));
+    });
+    
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
+            Err(ChangoEditorError::Timeout(file.path.clone()))
+        }
+        Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
+            Err(ChangoEditorError::ProcessorPanic("处理线程意外退出".to_string()))
+        }
+    }
+}
+
+/// 从 panic 负载中提取可读的消息
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "未知错误".to_string()
+    }
+}
+
+/// 进度回调: `(已完成数, 总数)`
+pub type ProgressCallback = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+/// 单次批处理的可选行为
+#[derive(Clone, Default)]
+struct BatchOptions {
+    cancel: Option<Arc<AtomicBool>>,
+    progress: Option<ProgressCallback>,
+    timeout: Option<Duration>,
+}
+
+/// 代码格式化器
+pub struct CodeFormatter;
+
+impl FileProcessor for CodeFormatter {
+    fn process(&self, file: &FileInfo) -> Result<()> {
+        match file.language {
+            Language::Rust => {
+                log::info!("格式化Rust代码: {}", file.name);
+                // 模拟rustfmt处理
+                thread::sleep(Duration::from_millis(100));
+            }
+            Language::Python => {
+                log::info!("格式化Python代码: {}", file.name);
+                // 模拟black处理
+                thread::sleep(Duration::from_millis(80));
+            }
+            Language::JavaScript => {
+                log::info!("格式化JavaScript代码: {}", file.name);
+                // 模拟prettier处理
+                thread::sleep(Duration::from_millis(60));
+            }
+            _ => {
+                log::debug!("跳过不支持的语言: {} ({})", file.name, file.language);
+            }
+        }
+        Ok(())
+    }
+    
+    fn get_name(&self) -> &str {
+        "CodeFormatter"
+    }
+}
+
+/// 外部格式化工具命令，文件路径作为最后一个参数追加
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatterCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    /// 从标准输入读取源码、把结果写到标准输出时使用的参数，`{file}` 替换为文件路径
+    ///
+    /// 预览 (`process_dry_run`) 优先使用此模式，工作目录为文件所在目录，
+    /// 工具因此能找到与原地格式化时相同的项目配置 (如 `rustfmt.toml`、`.prettierrc`)。
+    pub stdin_args: Option<Vec<String>>,
+}
+
+impl FormatterCommand {
+    pub fn new<S: Into<String>>(program: S, args: &[&str]) -> Self {
+        Self {
+            program: program.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            stdin_args: None,
+        }
+    }
+    
+    /// 设置标准输入模式的参数
+    pub fn stdin(mut self, args: &[&str]) -> Self {
+        self.stdin_args = Some(args.iter().map(|arg| arg.to_string()).collect());
+        self
+    }
+}
+
+/// 调用外部工具 (rustfmt、black、prettier 等) 原地格式化文件
+///
+/// 每种语言对应一条可配置的命令；没有配置命令的语言直接跳过。
+#[derive(Debug, Clone, Default)]
+pub struct ExternalFormatter {
+    commands: HashMap<Language, FormatterCommand>,
+}
+
+impl ExternalFormatter {
+    /// 创建未配置任何命令的格式化器
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 使用常见工具的默认配置
+    pub fn with_defaults() -> Self {
+        Self::new()
+            .command(Language::Rust, FormatterCommand::new("rustfmt", &["--edition", "2021"])
+                .stdin(&["--edition", "2021"]))
+            .command(Language::Python, FormatterCommand::new("black", &["--quiet"])
+                .stdin(&["--quiet", "--stdin-filename", "{file}", "-"]))
+            .command(Language::JavaScript, FormatterCommand::new("prettier", &["--write"])
+                .stdin(&["--stdin-filepath", "{file}"]))
+            .command(Language::TypeScript, FormatterCommand::new("prettier", &["--write"])
+                .stdin(&["--stdin-filepath", "{file}"]))
+    }
+    
+    /// 设置某种语言的格式化命令
+    pub fn command(mut self, language: Language, command: FormatterCommand) -> Self {
+        self.commands.insert(language, command);
+        self
+    }
+    
+    /// 获取某种语言的格式化命令
+    pub fn get_command(&self, language: Language) -> Option<&FormatterCommand> {
+        self.commands.get(&language)
+    }
+}
+
+impl ExternalFormatter {
+    /// 对指定路径运行格式化命令
+    fn run(command: &FormatterCommand, path: &Path) -> Result<()> {
+        let output = std::process::Command::new(Self::program(command)?)
+            .args(&command.args)
+            .arg(path)
+            .output()?;
+        Self::check_output(command, path, &output)
+    }
+    
+    /// 以标准输入模式格式化 `source`，返回格式化后的文本
+    fn run_stdin(command: &FormatterCommand, stdin_args: &[String], path: &Path, source: &str) -> Result<String> {
+        let file = path.to_string_lossy();
+        let mut process = std::process::Command::new(Self::program(command)?);
+        process
+            .args(stdin_args.iter().map(|arg| arg.replace("{file}", &file)))
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            process.current_dir(dir);
+        }
+        
+        let mut child = process.spawn()?;
+        // 另起线程写入，避免输出填满管道时双方互相等待
+        let mut stdin = child.stdin.take()
+            .ok_or_else(|| ChangoEditorError::IoError(io::Error::other("无法打开标准输入")))?;
+        let input = source.to_string();
+        let writer = thread::spawn(move || io::Write::write_all(&mut stdin, input.as_bytes()));
+        let output = child.wait_with_output()?;
+        let _ = writer.join();
+        
+        Self::check_output(command, path, &output)?;
+        String::from_utf8(output.stdout)
+            .map_err(|e| ChangoEditorError::ParseError(format!("{} 输出不是 UTF-8: {}", command.program, e)))
+    }
+    
+    fn program(command: &FormatterCommand) -> Result<PathBuf> {
+        find_program(&command.program).ok_or_else(|| {
+            ChangoEditorError::NotFound(format!("格式化工具未安装: {}", command.program))
+        })
+    }
+    
+    fn check_output(command: &FormatterCommand, path: &Path, output: &std::process::Output) -> Result<()> {
+        if !output.status.success() {
+            return Err(ChangoEditorError::ParseError(format!(
+                "{} 格式化 {:?} 失败: {}",
+                command.program,
+                path,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl FileProcessor for ExternalFormatter {
+    fn process(&self, file: &FileInfo) -> Result<()> {
+        match self.commands.get(&file.language) {
+            Some(command) => Self::run(command, &file.path),
+            None => Ok(()),
+        }
+    }
+    
+    fn get_name(&self) -> &str {
+        "ExternalFormatter"
+    }
+    
+    /// 以标准输入模式运行格式化命令 (未配置时改为格式化临时副本)，与原文件比较生成 diff
+    fn process_dry_run(&self, file: &FileInfo) -> Result<Option<String>> {
+        let command = match self.commands.get(&file.language) {
+            Some(command) => command,
+            None => return Ok(None),
+        };
+        
+        if let Some(stdin_args) = &command.stdin_args {
+            let original = fs::read_to_string(&file.path)?;
+            let formatted = Self::run_stdin(command, stdin_args, &file.path, &original)?;
+            if original == formatted {
+                return Ok(None);
+            }
+            return Ok(Some(unified_diff(&original, &formatted, &file.path)));
+        }
+        
+        // 副本保留原文件名，使工具能按扩展名识别语言；
+        // 副本不在项目目录中，工具读不到项目配置，预览可能与原地格式化的结果不同
+        let temp_dir = std::env::temp_dir().join(format!("chango_format_{}", Uuid::new_v4()));
+        fs::create_dir_all(&temp_dir)?;
+        let copy = temp_dir.join(&file.name);
+        
+        let result = fs::copy(&file.path, &copy)
+            .map_err(ChangoEditorError::from)
+            .and_then(|_| Self::run(command, &copy))
+            .and_then(|_| Ok((fs::read_to_string(&file.path)?, fs::read_to_string(&copy)?)));
+        let _ = fs::remove_dir_all(&temp_dir);
+        
+        let (original, formatted) = result?;
+        if original == formatted {
+            return Ok(None);
+        }
+        
+        Ok(Some(unified_diff(&original, &formatted, &file.path)))
+    }
+}
+
+/// 诊断严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// 单条诊断信息，行列号从 1 开始
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// 产生诊断信息的文件处理器 (如 linter)
+pub trait DiagnosticProcessor: Send + Sync {
+    fn diagnose(&self, file: &FileInfo) -> Result<Vec<Diagnostic>>;
+    fn get_name(&self) -> &str;
+}
+
+/// 并发诊断执行器，按文件 ID 汇总诊断结果
+///
+/// 与 [`ConcurrentFileProcessor`] 一样使用按需创建、跨批次复用的线程池。
+pub struct ConcurrentDiagnosticRunner<T: DiagnosticProcessor> {
+    processor: Arc<T>,
+    worker_count: usize,
+    pool: Mutex<Option<WorkerPool>>,
+}
+
+impl<T: DiagnosticProcessor + 'static> ConcurrentDiagnosticRunner<T> {
+    pub fn new(processor: T, worker_count: usize) -> Self {
+        Self {
+            processor: Arc::new(processor),
+            worker_count,
+            pool: Mutex::new(None),
+        }
+    }
+    
+    /// 并发诊断文件列表，单个文件失败或 panic 只影响该文件的结果
+    pub fn run(&self, files: Vec<FileInfo>) -> Result<HashMap<Uuid, Result<Vec<Diagnostic>>>> {
+        let ids: Vec<Uuid> = files.iter().map(|file| file.id).collect();
+        let jobs = self.pool_sender(files.len());
+        let (done_tx, done_rx) = crossbeam_channel::unbounded::<(Uuid, Result<Vec<Diagnostic>>)>();
+        
+        for file in files {
+            let processor = self.processor.clone();
+            let done_tx = done_tx.clone();
+            
+            let job: Job = Box::new(move |worker_id| {
+                log::debug!("工作线程 {} 诊断文件: {}", worker_id, file.name);
+                let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| processor.diagnose(&file)))
+                    .unwrap_or_else(|payload| Err(ChangoEditorError::ProcessorPanic(panic_message(&*payload))));
+                let _ = done_tx.send((file.id, result));
+            });
+            
+            jobs.send(job).map_err(|_| {
+                ChangoEditorError::ProcessorPanic("线程池已关闭".to_string())
+            })?;
+        }
+        drop(done_tx);
+        
+        // 所有任务结束 (包括意外退出) 后通道关闭
+        let mut results: HashMap<Uuid, Result<Vec<Diagnostic>>> = done_rx.into_iter().collect();
+        for id in ids {
+            results.entry(id).or_insert_with(|| {
+                Err(ChangoEditorError::ProcessorPanic("工作线程异常退出，文件未处理".to_string()))
+            });
+        }
+        
+        Ok(results)
+    }
+    
+    /// 获取线程池的任务发送端，首次调用时创建线程池
+    fn pool_sender(&self, file_count: usize) -> crossbeam_channel::Sender<Job> {
+        let mut pool = self.pool.lock().unwrap_or_else(|e| e.into_inner());
+        let pool = pool.get_or_insert_with(WorkerPool::new);
+        pool.grow_to(self.worker_count.max(1).min(file_count.max(1)));
+        pool.sender.clone()
+    }
+}
+
+impl<T: DiagnosticProcessor> Drop for ConcurrentDiagnosticRunner<T> {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.get_mut().unwrap_or_else(|e| e.into_inner()).take() {
+            pool.shutdown();
+        }
+    }
+}
+
+/// 调用 ruff 检查 Python 文件并解析其 JSON 输出，其他语言不产生诊断
+#[derive(Debug, Clone, Default)]
+pub struct RuffLinter;
+
+impl DiagnosticProcessor for RuffLinter {
+    fn diagnose(&self, file: &FileInfo) -> Result<Vec<Diagnostic>> {
+        if file.language != Language::Python {
+            return Ok(Vec::new());
+        }
+        
+        let program = find_program("ruff")
+            .ok_or_else(|| ChangoEditorError::NotFound("检查工具未安装: ruff".to_string()))?;
+        
+        let output = std::process::Command::new(program)
+            .args(["check", "--output-format", "json", "--exit-zero"])
+            .arg(&file.path)
+            .output()?;
+        
+        if !output.status.success() {
+            return Err(ChangoEditorError::ParseError(format!(
+                "ruff 检查 {:?} 失败: {}",
+                file.path,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        
+        let entries: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)
+            .map_err(|e| ChangoEditorError::ParseError(format!("无法解析 ruff 输出: {}", e)))?;
+        
+        Ok(entries
+            .iter()
+            .map(|entry| {
+                let location = &entry["location"];
+                let code = entry["code"].as_str().unwrap_or_default();
+                let message = entry["message"].as_str().unwrap_or_default();
+                
+                Diagnostic {
+                    line: location["row"].as_u64().unwrap_or(1) as usize,
+                    column: location["column"].as_u64().unwrap_or(1) as usize,
+                    // 语法错误没有规则代码
+                    severity: if code.is_empty() { Severity::Error } else { Severity::Warning },
+                    message: if code.is_empty() { message.to_string() } else { format!("{} {}", code, message) },
+                }
+            })
+            .collect())
+    }
+    
+    fn get_name(&self) -> &str {
+        "RuffLinter"
+    }
+}
+
+/// 项目文件变更事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileEvent {
+    Created(PathBuf),
+    Modified(PathBuf),
+    Removed(PathBuf),
+    Renamed { from: PathBuf, to: PathBuf },
+}
+
+impl FileEvent {
+    /// 事件涉及的 (最新) 路径
+    pub fn path(&self) -> &Path {
+        match self {
+            FileEvent::Created(path) | FileEvent::Modified(path) | FileEvent::Removed(path) => path,
+            FileEvent::Renamed { to, .. } => to,
+        }
+    }
+    
+    /// 把 `from` 之下的路径改写到 `to` 之下，其他路径保持不变
+    fn rebase(self, from: &Path, to: &Path) -> Self {
+        let rebase = |path: PathBuf| match path.strip_prefix(from) {
+            Ok(relative) => to.join(relative),
+            Err(_) => path,
+        };
+        match self {
+            FileEvent::Created(path) => FileEvent::Created(rebase(path)),
+            FileEvent::Modified(path) => FileEvent::Modified(rebase(path)),
+            FileEvent::Removed(path) => FileEvent::Removed(rebase(path)),
+            FileEvent::Renamed { from, to } => FileEvent::Renamed { from: rebase(from), to: rebase(to) },
+        }
+    }
+}
+
+/// 项目目录监听器
+///
+/// 监听项目目录的文件系统变化，按排除模式与扫描扩展名过滤后同步更新项目的文件索引，
+/// 并把实际生效的变更通过 `events()` 通道转发给调用方。
+/// 同一路径在防抖窗口内的连续事件 (如编辑器保存时的多次写入) 会被合并为一次。
+/// 只能监听本地磁盘上的项目，使用自定义文件系统的项目返回 `ValidationError`。
+pub struct ProjectWatcher {
+    _watcher: notify::RecommendedWatcher,
+    events: crossbeam_channel::Receiver<FileEvent>,
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl ProjectWatcher {
+    /// 开始监听项目目录，`debounce` 为防抖窗口
+    pub fn new(project: Arc<Project>, debounce: Duration) -> Result<Self> {
+        if project.file_system.is_some() {
+            return Err(ChangoEditorError::ValidationError(
+                "只能监听本地文件系统上的项目".to_string()
+            ));
+        }
+        let exclude_set = project.config.exclude_set()?;
+        let hidden_filter = project.config.hidden_filter()?;
+        let extensions = project.config.extension_set();
+        let (raw_tx, raw_rx) = crossbeam_channel::unbounded();
+        
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                let _ = raw_tx.send(event);
+            }
+        })
+        .map_err(|e| ChangoEditorError::IoError(io::Error::other(e)))?;
+        
+        notify::Watcher::watch(&mut watcher, &project.path, notify::RecursiveMode::Recursive)
+            .map_err(|e| ChangoEditorError::IoError(io::Error::other(e)))?;
+        
+        // notify 报告规范化的绝对路径，而索引沿用项目根目录的原始写法 (可能是相对路径)，
+        // 先映射回原始写法，排除模式、隐藏文件判断与按路径查找才能匹配
+        let watched_root = fs::canonicalize(&project.path)?;
+        
+        let (event_tx, event_rx) = crossbeam_channel::unbounded();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        
+        let handle = thread::spawn(move || {
+            let mut pending: Vec<FileEvent> = Vec::new();
+            
+            while !thread_stop.load(Ordering::Relaxed) {
+                // 有待处理事件时，窗口内没有新事件到达才提交
+                let wait = if pending.is_empty() { Duration::from_millis(200) } else { debounce };
+                
+                match raw_rx.recv_timeout(wait) {
+                    Ok(event) => {
+                        let relevant = |path: &Path| {
+                            !is_excluded(&exclude_set, &project.path, path)
+                                && !is_hidden(hidden_filter.as_ref(), &project.path, path)
+                                && is_scanned_extension(&extensions, path)
+                        };
+                        
+                        for change in translate_notify_event(event) {
+                            match change.rebase(&watched_root, &project.path) {
+                                // 移出监听范围的重命名等同于删除
+                                FileEvent::Renamed { from, to } if !relevant(&to) && relevant(&from) => {
+                                    coalesce_event(&mut pending, FileEvent::Removed(from));
+                                }
+                                change if relevant(change.path()) => coalesce_event(&mut pending, change),
+                                _ => {}
+                            }
+                        }
+                    }
+                    Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
+                        for change in pending.drain(..) {
+                            if apply_file_event(&project, &change) {
+                                let _ = event_tx.send(change);
+                            }
+                        }
+                    }
+                    Err(crossbeam_channel::RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+        
+        Ok(Self {
+            _watcher: watcher,
+            events: event_rx,
+            stop,
+            handle: Some(handle),
+        })
+    }
+    
+    /// 已应用到索引的变更事件
+    pub fn events(&self) -> &crossbeam_channel::Receiver<FileEvent> {
+        &self.events
+    }
+}
+
+impl Drop for ProjectWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// 自动保存回调，返回 `Ok` 表示文件已保存
+pub type SaveCallback = Arc<dyn Fn(&FileInfo) -> Result<()> + Send + Sync>;
+
+/// 自动保存句柄，丢弃时停止后台线程
+pub struct AutoSaveHandle {
+    callback: Arc<Mutex<Option<SaveCallback>>>,
+    stop: Option<crossbeam_channel::Sender<()>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl AutoSaveHandle {
+    /// 注册保存回调，替换之前注册的回调
+    pub fn on_save<F>(&self, save: F)
+    where
+        F: Fn(&FileInfo) -> Result<()> + Send + Sync + 'static,
+    {
+        *self.callback.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(save));
+    }
+    
+    /// 自动保存线程是否在运行 (配置未开启自动保存时为 false)
+    pub fn is_running(&self) -> bool {
+        self.handle.is_some()
+    }
+}
+
+impl Drop for AutoSaveHandle {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// 自动处理的防抖窗口
+const AUTO_PROCESS_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 自动处理器: 监听项目目录，对每个变更的文件运行 [`FileProcessor`] (如格式化)
+///
+/// 待处理文件按路径去重，同一文件在处理前的多次保存只处理一次，排队的工作量不超过变更的文件数。
+/// 处理器自身写回文件引起的变更会被识别并跳过，不会循环触发。
+pub struct AutoProcessor;
+
+impl AutoProcessor {
+    /// 开始监听并处理，返回的句柄丢弃或调用 `shutdown` 时停止
+    pub fn run(project: Arc<Project>, processor: Arc<dyn FileProcessor>) -> Result<AutoProcessorHandle> {
+        let watcher = ProjectWatcher::new(project.clone(), AUTO_PROCESS_DEBOUNCE)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let processed = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let thread_stop = stop.clone();
+        let thread_processed = processed.clone();
+        
+        let handle = thread::spawn(move || {
+            let mut pending: std::collections::VecDeque<PathBuf> = std::collections::VecDeque::new();
+            let mut queued: HashSet<PathBuf> = HashSet::new();
+            // 处理完成后文件的修改时间，用于识别处理器自身的写回
+            let mut processed_at: HashMap<PathBuf, SystemTime> = HashMap::new();
+            
+            while !thread_stop.load(Ordering::Relaxed) {
+                // 先取走所有已到达的事件再处理下一个文件，空闲时短暂阻塞等待
+                let first = if pending.is_empty() {
+                    match watcher.events().recv_timeout(Duration::from_millis(200)) {
+                        Ok(event) => Some(event),
+                        Err(crossbeam_channel::RecvTimeoutError::Timeout) => continue,
+                        Err(crossbeam_channel::RecvTimeoutError::Disconnected) => break,
+                    }
+                } else {
+                    None
+                };
+                
+                for event in first.into_iter().chain(watcher.events().try_iter()) {
+                    match event {
+                        FileEvent::Removed(path) => {
+                            processed_at.remove(&path);
+                        }
+                        event => {
+                            let path = event.path().to_path_buf();
+                            if queued.insert(path.clone()) {
+                                pending.push_back(path);
+                            }
+                        }
+                    }
+                }
+                
+                let Some(path) = pending.pop_front() else { continue };
+                queued.remove(&path);
+                
+                let modified_at = project.file_system().metadata(&path).ok().and_then(|m| m.modified);
+                if modified_at.is_some() && processed_at.get(&path) == modified_at.as_ref() {
+                    continue;
+                }
+                
+                let Some(file_info) = project.find_file_by_path(&path) else { continue };
+                match processor.process(&file_info) {
+                    Ok(()) => {
+                        thread_processed.fetch_add(1, Ordering::Relaxed);
+                        if let Some(modified_at) = project.file_system().metadata(&path).ok().and_then(|m| m.modified) {
+                            processed_at.insert(path, modified_at);
+                        }
+                    }
+                    Err(e) => log::warn!("{} 处理失败 {:?}: {}", processor.get_name(), path, e),
+                }
+            }
+        });
+        
+        Ok(AutoProcessorHandle {
+            stop,
+            processed,
+            handle: Some(handle),
+        })
+    }
+}
+
+/// 自动处理句柄，丢弃时停止监听并等待正在处理的文件完成
+pub struct AutoProcessorHandle {
+    stop: Arc<AtomicBool>,
+    processed: Arc<std::sync::atomic::AtomicUsize>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl AutoProcessorHandle {
+    /// 已成功处理的文件次数
+    pub fn processed_count(&self) -> usize {
+        self.processed.load(Ordering::Relaxed)
+    }
+    
+    /// 停止监听，等待正在处理的文件完成；尚未开始处理的文件被丢弃
+    pub fn shutdown(mut self) {
+        self.stop_and_join();
+    }
+    
+    fn stop_and_join(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for AutoProcessorHandle {
+    fn drop(&mut self) {
+        self.stop_and_join();
+    }
+}
+
+/// 将 notify 的原始事件转换为文件变更事件
+fn translate_notify_event(event: notify::Event) -> Vec<FileEvent> {
+    use notify::event::{EventKind, ModifyKind, RenameMode};
+    
+    let mut paths = event.paths.into_iter();
+    match event.kind {
+        EventKind::Create(_) => paths.map(FileEvent::Created).collect(),
+        EventKind::Remove(_) => paths.map(FileEvent::Removed).collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            match (paths.next(), paths.next()) {
+                (Some(from), Some(to)) => vec![FileEvent::Renamed { from, to }],
+                _ => Vec::new(),
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => paths.map(FileEvent::Removed).collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => paths.map(FileEvent::Created).collect(),
+        EventKind::Modify(ModifyKind::Metadata(_)) => Vec::new(),
+        EventKind::Modify(_) => paths.map(FileEvent::Modified).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// 将新事件合并进防抖队列中同一路径的既有事件
+fn coalesce_event(pending: &mut Vec<FileEvent>, event: FileEvent) {
+    if let FileEvent::Renamed { from, to } = &event {
+        // 部分平台会同时发出拆分的 From/To 事件，完整的重命名事件取代它们
+        pending.retain(|e| match e {
+            FileEvent::Removed(path) => path != from,
+            FileEvent::Created(path) => path != to,
+            _ => true,
+        });
+        pending.push(event);
+        return;
+    }
+    
+    let existing = pending.iter().position(|e| {
+        !matches!(e, FileEvent::Renamed { .. }) && e.path() == event.path()
+    });
+    
+    let index = match existing {
+        Some(index) => index,
+        None => {
+            pending.push(event);
+            return;
+        }
+    };
+    
+    let merged = match (&pending[index], &event) {
+        // 新建后又修改仍是新建；新建后删除则相互抵消
+        (FileEvent::Created(_), FileEvent::Modified(_)) => None,
+        (FileEvent::Created(_), FileEvent::Removed(_)) => {
+            pending.remove(index);
+            return;
+        }
+        // 删除后重新出现视为修改 (编辑器的原子保存)
+        (FileEvent::Removed(path), FileEvent::Created(_)) => Some(FileEvent::Modified(path.clone())),
+        _ => Some(event),
+    };
+    
+    if let Some(merged) = merged {
+        pending[index] = merged;
+    }
+}
+
+/// 将变更事件应用到项目索引，返回索引是否发生变化
+fn apply_file_event(project: &Project, event: &FileEvent) -> bool {
+    match event {
+        FileEvent::Created(path) | FileEvent::Modified(path) => reindex_path(project, path),
+        FileEvent::Removed(path) => {
+            // 删除目录时一并移除其下所有已索引文件
+            let ids: Vec<Uuid> = project.get_all_files()
+                .into_iter()
+                .filter(|file| file.path.starts_with(path))
+                .map(|file| file.id)
+                .collect();
+            
+            for id in &ids {
+                let _ = project.remove_file(id);
+            }
+            !ids.is_empty()
+        }
+        FileEvent::Renamed { from, to } => match project.find_file_by_path(from) {
+            Some(file) => project.rename_file(&file.id, to.clone()).is_ok(),
+            None => reindex_path(project, to),
+        },
+    }
+}
+
+/// 重新读取单个路径并更新索引，已索引的文件保留原有 ID
+fn reindex_path(project: &Project, path: &Path) -> bool {
+    let existing = project.find_file_by_path(path).map(|file| file.id);
+    
+    match project.read_file_info(path) {
+        Ok(mut file_info) if Project::should_index(&file_info) => {
+            if let Some(id) = existing {
+                file_info.id = id;
+            }
+            project.add_file(file_info).is_ok()
+        }
+        _ => match existing {
+            Some(id) => project.remove_file(&id).is_ok(),
+            None => false,
+        },
+    }
+}
+
+/// 异步文件服务
+pub struct AsyncFileService {
+    project_manager: Arc<ProjectManager>,
+}
+
+impl AsyncFileService {
+    pub fn new(project_manager: Arc<ProjectManager>) -> Self {
+        Self { project_manager }
+    }
+    
+    /// 异步扫描项目
+    ///
+    /// `cancel` 被触发后扫描在下一个文件处停止并返回 `ChangoEditorError::Cancelled`。
+    pub async fn scan_project_async(
+        &self,
+        project_id: Uuid,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<ScanReport> {
+        let project = self.project_manager
+            .get_project(&project_id)
+            .ok_or(ChangoEditorError::ProjectNotFound(project_id))?;
+        
+        // 在异步上下文中执行CPU密集型任务
+        let project_clone = project.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            project_clone.scan_files_until(None, &|| cancel.is_cancelled())
+        }).await;
+        
+        match result {
+            Ok(report) => report,
+            Err(e) => Err(ChangoEditorError::ParseError(format!("异步任务失败: {}", e))),
+        }
+    }
+    
+    /// 限时扫描项目: 超过 `deadline` 后在下一个文件处停止，返回已索引的部分结果
+    ///
+    /// 文件按批写入索引，截止时已读完的文件保留在项目中，`completed` 为 false。
+    pub async fn scan_project_async_timeout(
+        &self,
+        project_id: Uuid,
+        deadline: Duration,
+    ) -> Result<PartialScan> {
+        let project = self.project_manager
+            .get_project(&project_id)
+            .ok_or(ChangoEditorError::ProjectNotFound(project_id))?;
+        
+        // 从调用时开始计时，排队等待阻塞线程的时间也计入；溢出视为不设截止时间
+        let deadline = Instant::now().checked_add(deadline);
+        let result = tokio::task::spawn_blocking(move || {
+            project.scan_files_partial(&|| deadline.is_some_and(|deadline| Instant::now() >= deadline))
+        }).await;
+        
+        match result {
+            Ok(scan) => scan,
+            Err(e) => Err(ChangoEditorError::ParseError(format!("异步任务失败: {}", e))),
+        }
+    }
+    
+    /// 流式扫描项目，每索引一个文件就产出一项
+    ///
+    /// 无法读取的文件产出 `Err` 后继续扫描；遍历目录失败时产出错误并结束。
+    /// 丢弃返回的流会让后台扫描在下一个文件处停止。需要在 tokio 运行时内调用。
+    pub fn scan_project_stream(&self, project_id: Uuid) -> impl tokio_stream::Stream<Item = Result<FileInfo>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(64);
+        let project = self.project_manager.get_project(&project_id);
+        
+        tokio::task::spawn_blocking(move || {
+            let project = match project {
+                Some(project) => project,
+                None => {
+                    let _ = tx.blocking_send(Err(ChangoEditorError::ProjectNotFound(project_id)));
+                    return;
+                }
+            };
+            
+            let candidates = match project.collect_candidates(None) {
+                Ok(candidates) => candidates,
+                Err(e) => {
+                    let _ = tx.blocking_send(Err(e));
+                    return;
+                }
+            };
+            
+            for path in candidates {
+                let item = match project.read_file_info(&path) {
+                    Ok(file_info) if Project::should_index(&file_info) => {
+                        project.add_file(file_info.clone()).map(|id| FileInfo { id, ..file_info })
+                    }
+                    Ok(_) => continue,
+                    Err(e) => Err(e),
+                };
+                
+                // 接收端已关闭，停止扫描
+                if tx.blocking_send(item).is_err() {
+                    break;
+                }
+            }
+        });
+        
+        tokio_stream::wrappers::ReceiverStream::new(rx)
+    }
+    
+    /// 带进度的流式扫描
+    ///
+    /// 与限时扫描共用分批循环: 每批写入索引后为其中每个文件产出 `FileIndexed`、为每个跳过的文件
+    /// 产出 `Error`，再产出一个 `Progress`；全部处理完后产出 `Done`，其中 `skipped` 与
+    /// `ScanReport::skipped` 口径一致。项目不存在或遍历目录失败时只产出一个 `Error` 并结束。
+    /// 丢弃返回的流会让后台扫描在下一批处停止。需要在 tokio 运行时内调用。
+    pub fn scan_project_events(&self, project_id: Uuid) -> impl tokio_stream::Stream<Item = ScanEvent> {
+        let (tx, rx) = tokio::sync::mpsc::channel(64);
+        let project = self.project_manager.get_project(&project_id);
+        
+        tokio::task::spawn_blocking(move || {
+            let project = match project {
+                Some(project) => project,
+                None => {
+                    let _ = tx.blocking_send(ScanEvent::error(None, &ChangoEditorError::ProjectNotFound(project_id)));
+                    return;
+                }
+            };
+            
+            let mut indexed = 0;
+            let mut skipped = 0;
+            let mut done = 0;
+            
+            // 接收端关闭后发送失败，扫描在下一批处停止
+            let result = project.scan_in_batches(&|| tx.is_closed(), |batch, total| {
+                indexed += batch.files.len();
+                skipped += batch.skipped.len();
+                done += batch.processed;
+                
+                let files = batch.files.into_iter().map(|file| ScanEvent::FileIndexed { file });
+                let errors = batch.skipped.into_iter().map(|(path, e)| ScanEvent::error(Some(path), &e));
+                for event in files.chain(errors).chain([ScanEvent::Progress { done, total }]) {
+                    if tx.blocking_send(event).is_err() {
+                        return;
+                    }
+                }
+            });
+            
+            match result {
+                Ok(true) => {
+                    let _ = tx.blocking_send(ScanEvent::Done { indexed, skipped });
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    let _ = tx.blocking_send(ScanEvent::error(None, &e));
+                }
+            }
+        });
+        
+        tokio_stream::wrappers::ReceiverStream::new(rx)
+    }
+    
+    /// 异步搜索文件
+    ///
+    /// `cancel` 被触发后搜索在下一个文件处停止并返回 `ChangoEditorError::Cancelled`。
+    pub async fn search_files_async(
+        &self,
+        project_id: Uuid,
+        query: String,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Vec<FileInfo>> {
+        let project = self.project_manager
+            .get_project(&project_id)
+            .ok_or(ChangoEditorError::ProjectNotFound(project_id))?;
+        
+        let project_clone = project.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            project_clone.search_files_until(&query, SearchMode::Substring, &|| cancel.is_cancelled())
+        }).await;
+        
+        match result {
+            Ok(files) => files,
+            Err(e) => Err(ChangoEditorError::ParseError(format!("搜索失败: {}", e))),
+        }
+    }
+}
+
+// 工具函数
+
+/// 流式行计数器
+///
+/// 按块统计换行符，末尾没有换行符的残行也计为一行；空文件为 0 行。
+/// `\r\n` 按一个换行计算。
+#[derive(Debug, Default)]
+struct LineCounter {
+    newlines: usize,
+    crlf: usize,
+    last_byte: Option<u8>,
+}
+
+impl LineCounter {
+    fn feed(&mut self, chunk: &[u8]) {
+        for (i, &byte) in chunk.iter().enumerate() {
+            if byte == b'\n' {
+                self.newlines += 1;
+                // \r 可能位于上一块的末尾
+                let previous = if i > 0 { Some(chunk[i - 1]) } else { self.last_byte };
+                if previous == Some(b'\r') {
+                    self.crlf += 1;
+                }
+            }
+        }
+        if let Some(&last) = chunk.last() {
+            self.last_byte = Some(last);
+        }
+    }
+    
+    fn line_ending(&self) -> LineEnding {
+        match (self.crlf, self.newlines - self.crlf) {
+            (0, _) => LineEnding::Lf,
+            (_, 0) => LineEnding::CrLf,
+            _ => LineEnding::Mixed,
+        }
+    }
+    
+    fn finish(&self) -> usize {
+        match self.last_byte {
+            None | Some(b'\n') => self.newlines,
+            Some(_) => self.newlines + 1,
+        }
+    }
+}
+
+/// 以固定大小的块顺序读取文件，避免一次性载入内存
+fn for_each_chunk<P, F>(file_system: &dyn FileSystem, path: P, f: F) -> Result<()>
+where
+    P: AsRef<Path>,
+    F: FnMut(&[u8]),
+{
+    read_chunks(file_system.open(path.as_ref())?, f)
+}
+
+/// 按块读取任意数据源，依次交给回调处理
+fn read_chunks<R: Read, F: FnMut(&[u8])>(reader: R, mut f: F) -> Result<()> {
+    let mut reader = BufReader::with_capacity(READ_BUFFER_SIZE, reader);
+    
+    loop {
+        let chunk = reader.fill_buf()?;
+        if chunk.is_empty() {
+            break;
+        }
+        f(chunk);
+        let len = chunk.len();
+        reader.consume(len);
+    }
+    
+    Ok(())
+}
+
+/// 检查一组排除风格的模式，`kind` 用于错误信息
+fn validate_patterns(patterns: &[String], kind: &str) -> Result<()> {
+    let invalid: Vec<String> = patterns
+        .iter()
+        .filter_map(|pattern| {
+            expand_exclude_pattern(pattern)
+                .iter()
+                .find_map(|variant| globset::Glob::new(variant).err())
+                .map(|e| format!("{} ({})", pattern, e))
+        })
+        .collect();
+    
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(ChangoEditorError::ValidationError(
+            format!("无效的{}: {}", kind, invalid.join("; ")),
+        ))
+    }
+}
+
+/// 把一组排除风格的模式编译为 GlobSet
+fn build_pattern_set(patterns: &[String], kind: &str) -> Result<globset::GlobSet> {
+    let mut builder = globset::GlobSetBuilder::new();
+    
+    for pattern in patterns {
+        for variant in expand_exclude_pattern(pattern) {
+            let glob = globset::Glob::new(&variant).map_err(|e| {
+                ChangoEditorError::ValidationError(format!("无效的{} {}: {}", kind, pattern, e))
+            })?;
+            builder.add(glob);
+        }
+    }
+    
+    builder.build()
+        .map_err(|e| ChangoEditorError::ValidationError(e.to_string()))
+}
+
+/// 相似度检测中每个指纹覆盖的连续行数
+const SHINGLE_LINES: usize = 3;
+
+/// 文本的行指纹集合: 去掉空行并规范化空白后，每连续 `SHINGLE_LINES` 行计算一个哈希
+///
+/// 只有单行的重复 (如 `}`) 不足以让两个文件相似；不足 `SHINGLE_LINES` 行的文本整体作为一个指纹。
+fn line_shingles(text: &str) -> HashSet<u64> {
+    let lines: Vec<String> = text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect();
+    
+    if lines.is_empty() {
+        return HashSet::new();
+    }
+    
+    lines.windows(SHINGLE_LINES.min(lines.len()))
+        .map(|window| xxhash_rust::xxh3::xxh3_64(window.join("\n").as_bytes()))
+        .collect()
+}
+
+/// 展开排除模式的所有等价形式
+fn expand_exclude_pattern(pattern: &str) -> Vec<String> {
+    let (anchored, pattern) = match pattern.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    
+    let mut bases = vec![pattern.to_string()];
+    if let Some(dir) = pattern.strip_suffix("/**").or_else(|| pattern.strip_suffix("/*")) {
+        bases.push(dir.to_string());
+    }
+    
+    let mut variants = bases.clone();
+    if !anchored {
+        variants.extend(
+            bases.iter()
+                .filter(|base| !base.starts_with("**/"))
+                .map(|base| format!("**/{}", base)),
+        );
+    }
+    variants
+}
+
+/// 检查文件扩展名是否在扫描范围内 (不区分大小写，无扩展名的文件留待 shebang 检测)
+fn is_scanned_extension(extensions: &HashSet<String>, path: &Path) -> bool {
+    match path.extension() {
+        Some(extension) => extension.to_str()
+            .is_some_and(|extension| extensions.contains(&extension.to_lowercase())),
+        None => true,
+    }
+}
+
+/// 路径相对 `root` 的某一部分以 `.` 开头，且到该部分为止的路径未被包含模式放行 (根目录本身不算)
+///
+/// `hidden_filter` 为 `None` 表示包含全部隐藏文件，见 [`ProjectConfig::hidden_filter`]。
+fn is_hidden(hidden_filter: Option<&globset::GlobSet>, root: &Path, path: &Path) -> bool {
+    let (Some(allowed), Ok(relative)) = (hidden_filter, path.strip_prefix(root)) else {
+        return false;
+    };
+    let mut prefix = PathBuf::new();
+    relative.components().any(|c| {
+        prefix.push(c);
+        c.as_os_str().to_str().is_some_and(|name| name.starts_with('.')) && !allowed.is_match(&prefix)
+    })
+}
+
+/// 判断路径是否被排除 (项目根目录本身永不排除)
+fn is_excluded(exclude_set: &globset::GlobSet, root: &Path, path: &Path) -> bool {
+    match path.strip_prefix(root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => exclude_set.is_match(relative),
+        _ => false,
+    }
+}
+
+/// 多个路径解析到同一个真实文件时只保留一个
+///
+/// 优先保留不经过符号链接的路径，其次保留字典序最小的路径；无法解析的路径原样保留。
+fn dedup_real_paths(root: &Path, paths: &mut Vec<PathBuf>) {
+    let real_root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let is_direct = |real: &Option<PathBuf>, path: &Path| match (real, path.strip_prefix(root)) {
+        (Some(real), Ok(relative)) => *real == real_root.join(relative),
+        _ => false,
+    };
+    
+    let mut keyed: Vec<(Option<PathBuf>, PathBuf)> = paths
+        .drain(..)
+        .map(|path| (fs::canonicalize(&path).ok(), path))
+        .collect();
+    keyed.sort_by(|(a_real, a), (b_real, b)| {
+        is_direct(b_real, b).cmp(&is_direct(a_real, a)).then_with(|| a.cmp(b))
+    });
+    
+    let mut seen = HashSet::new();
+    for (real, path) in keyed {
+        if real.is_none_or(|real| seen.insert(real)) {
+            paths.push(path);
+        }
+    }
+}
+
+/// 模糊匹配时文件名命中的额外得分
+const FUZZY_NAME_BONUS: usize = 10;
+
+/// 子序列模糊匹配得分，不匹配时返回 `None`
+///
+/// 每个命中字符得 1 分，与上一个命中字符相邻再加 5 分，
+/// 位于开头或分隔符 (`/`、`_`、`-`、`.`) 之后再加 3 分。查询中的空白被忽略。
+fn fuzzy_score(candidate: &str, query: &str) -> Option<usize> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    
+    for query_char in query.chars().filter(|c| !c.is_whitespace()) {
+        let index = position + candidate[position..].iter().position(|&c| c == query_char)?;
+        
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || matches!(candidate[index - 1], '/' | '\\' | '_' | '-' | '.') {
+            score += 3;
+        }
+        
+        previous = Some(index);
+        position = index + 1;
+    }
+    
+    Some(score)
+}
+
+/// 行级差异操作，记录行在原文本/新文本中的下标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffOp {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Myers 差异算法，返回把 `old` 变为 `new` 的最短编辑序列
+///
+/// 使用线性空间的分治版本: 每次从两端同时搜索，在两条路径重叠处二分，内存为 O(N+M)。
+/// 同一段改动中删除行总在插入行之前。
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    diff_range(old, new, 0, 0, &mut ops);
+    
+    // 排序是稳定的，删除与插入各自保持原有顺序
+    for changes in ops.split_mut(|op| matches!(op, DiffOp::Equal(..))) {
+        changes.sort_by_key(|op| matches!(op, DiffOp::Insert(_)));
+    }
+    ops
+}
+
+/// 求子序列 `old`/`new` 的差异，`old_start`/`new_start` 为子序列在完整序列中的起始下标
+fn diff_range(old: &[&str], new: &[&str], old_start: usize, new_start: usize, ops: &mut Vec<DiffOp>) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    ops.extend((0..prefix).map(|i| DiffOp::Equal(old_start + i, new_start + i)));
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let (old_start, new_start) = (old_start + prefix, new_start + prefix);
+    
+    let suffix = old.iter().rev().zip(new.iter().rev()).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+    
+    if old.is_empty() {
+        ops.extend((0..new.len()).map(|i| DiffOp::Insert(new_start + i)));
+    } else if new.is_empty() {
+        ops.extend((0..old.len()).map(|i| DiffOp::Delete(old_start + i)));
+    } else {
+        let (x, y) = middle_split(old, new);
+        diff_range(&old[..x], &new[..y], old_start, new_start, ops);
+        diff_range(&old[x..], &new[y..], old_start + x, new_start + y, ops);
+    }
+    
+    let (old_end, new_end) = (old_start + old.len(), new_start + new.len());
+    ops.extend((0..suffix).map(|i| DiffOp::Equal(old_end + i, new_end + i)));
+}
+
+/// 从两端同时搜索最短编辑路径，返回两条路径重叠处的分割点 `(x, y)`
+///
+/// 只保留每条对角线当前到达的最远位置 (按 `k + offset` 下标，-1 表示尚未到达)。
+/// 调用方保证 `old`/`new` 都非空。
+fn middle_split(old: &[&str], new: &[&str]) -> (usize, usize) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max_d = (n + m + 1) / 2;
+    let offset = max_d;
+    let length = 2 * max_d + 2;
+    let mut forward = vec![-1isize; length as usize];
+    let mut backward = vec![-1isize; length as usize];
+    forward[offset as usize + 1] = 0;
+    backward[offset as usize + 1] = 0;
+    
+    let delta = n - m;
+    // 差值为奇数时正向路径先与反向路径重叠
+    let front = delta % 2 != 0;
+    // 越出编辑图边界的对角线不再继续搜索
+    let (mut forward_start, mut forward_end) = (0, 0);
+    let (mut backward_start, mut backward_end) = (0, 0);
+    
+    for d in 0..max_d {
+        for k in (-d + forward_start..d + 1 - forward_end).step_by(2) {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && forward[i - 1] < forward[i + 1]) {
+                forward[i + 1]
+            } else {
+                forward[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[i] = x;
+            
+            if x > n {
+                forward_end += 2;
+            } else if y > m {
+                forward_start += 2;
+            } else if front {
+                let j = offset + delta - k;
+                if (0..length).contains(&j) && backward[j as usize] != -1 && x >= n - backward[j as usize] {
+                    return (x as usize, y as usize);
+                }
+            }
+        }
+        
+        // 反向搜索在倒序的序列上进行，x/y 为距离末尾的行数
+        for k in (-d + backward_start..d + 1 - backward_end).step_by(2) {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && backward[i - 1] < backward[i + 1]) {
+                backward[i + 1]
+            } else {
+                backward[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[(n - x - 1) as usize] == new[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[i] = x;
+            
+            if x > n {
+                backward_end += 2;
+            } else if y > m {
+                backward_start += 2;
+            } else if !front {
+                let j = offset + delta - k;
+                if (0..length).contains(&j) && forward[j as usize] != -1 {
+                    let forward_x = forward[j as usize];
+                    if forward_x >= n - x {
+                        return (forward_x as usize, (forward_x - (j - offset)) as usize);
+                    }
+                }
+            }
+        }
+    }
+    
+    // 两端非空时总会重叠，这里只是兜底: 整体删除再整体插入
+    (old.len(), 0)
+}
+
+/// 生成带 3 行上下文的 unified diff
+///
+/// 行连同行尾符一起比较，只改变换行符 (CRLF/LF) 或末尾换行的修改也会产生 hunk；
+/// 与 GNU diff 一样，`\r` 原样输出，缺少末尾换行的行后跟 `\ No newline at end of file`。
+fn unified_diff(original: &str, modified: &str, path: &Path) -> String {
+    const CONTEXT: usize = 3;
+    
+    let old: Vec<&str> = original.split_inclusive('\n').collect();
+    let new: Vec<&str> = modified.split_inclusive('\n').collect();
+    let ops = diff_lines(&old, &new);
+    
+    let mut diff = format!("--- {}\n+++ {}\n", path.display(), path.display());
+    let changes: Vec<usize> = ops.iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+    
+    let mut i = 0;
+    while i < changes.len() {
+        // 间隔不超过两倍上下文的改动合并为同一个 hunk
+        let mut j = i;
+        while j + 1 < changes.len() && changes[j + 1] - changes[j] - 1 <= 2 * CONTEXT {
+            j += 1;
+        }
+        
+        let start = changes[i].saturating_sub(CONTEXT);
+        let end = (changes[j] + CONTEXT + 1).min(ops.len());
+        let hunk = &ops[start..end];
+        
+        // 计算 hunk 起点之前的行数
+        let (mut old_line, mut new_line) = (0, 0);
+        for op in &ops[..start] {
+            match op {
+                DiffOp::Equal(..) => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+                DiffOp::Delete(_) => old_line += 1,
+                DiffOp::Insert(_) => new_line += 1,
+            }
+        }
+        
+        let old_len = hunk.iter().filter(|op| !matches!(op, DiffOp::Insert(_))).count();
+        let new_len = hunk.iter().filter(|op| !matches!(op, DiffOp::Delete(_))).count();
+        let old_start = if old_len == 0 { old_line } else { old_line + 1 };
+        let new_start = if new_len == 0 { new_line } else { new_line + 1 };
+        diff.push_str(&format!("@@ -{},{} +{},{} @@\n", old_start, old_len, new_start, new_len));
+        
+        for op in hunk {
+            let (marker, line) = match *op {
+                DiffOp::Equal(a, _) => (' ', old[a]),
+                DiffOp::Delete(a) => ('-', old[a]),
+                DiffOp::Insert(b) => ('+', new[b]),
+            };
+            diff.push(marker);
+            match line.strip_suffix('\n') {
+                Some(line) => {
+                    diff.push_str(line);
+                    diff.push('\n');
+                }
+                None => {
+                    diff.push_str(line);
+                    diff.push_str("\n\\ No newline at end of file\n");
+                }
+            }
+        }
+        
+        i = j + 1;
+    }
+    
+    diff
+}
+
+/// 在 PATH 中查找可执行文件 (类似 `which`)，包含路径分隔符时直接检查该路径
+fn find_program(program: &str) -> Option<PathBuf> {
+    let candidate = Path::new(program);
+    if candidate.components().count() > 1 {
+        return candidate.is_file().then(|| candidate.to_path_buf());
+    }
+    
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths).find_map(|dir| {
+        let path = dir.join(program);
+        if path.is_file() {
+            return Some(path);
+        }
+        
+        let exe = path.with_extension(std::env::consts::EXE_EXTENSION);
+        (!std::env::consts::EXE_EXTENSION.is_empty() && exe.is_file()).then_some(exe)
+    })
+}
+
+/// 计算文件校验和
+fn calculate_checksum<P: AsRef<Path>>(
+    file_system: &dyn FileSystem,
+    path: P,
+    algorithm: &ChecksumAlgorithm,
+) -> Result<String> {
+    let mut hasher = ChecksumHasher::new(algorithm)?;
+    for_each_chunk(file_system, path, |chunk| hasher.update(chunk))?;
+    Ok(hasher.finish())
+}
+
+/// 流式编码嗅探器
+///
+/// 优先识别 BOM，其次校验整个文件是否为合法 UTF-8，最后交给 chardetng 统计检测；
+/// 统计检测也没有把握时标记为 "binary"。
+struct EncodingSniffer {
+    head: Vec<u8>,
+    utf8_valid: bool,
+    utf8_pending: Vec<u8>,
+    detector: chardetng::EncodingDetector,
+}
+
+impl EncodingSniffer {
+    fn new() -> Self {
+        Self {
+            head: Vec::with_capacity(3),
+            utf8_valid: true,
+            utf8_pending: Vec::new(),
+            detector: chardetng::EncodingDetector::new(),
+        }
+    }
+    
+    fn feed(&mut self, chunk: &[u8]) {
+        if self.head.len() < 3 {
+            let take = (3 - self.head.len()).min(chunk.len());
+            self.head.extend_from_slice(&chunk[..take]);
+        }
+        
+        if self.utf8_valid {
+            // 多字节字符可能被块边界截断，未完成的尾部留到下一块再校验
+            let data: std::borrow::Cow<[u8]> = if self.utf8_pending.is_empty() {
+                chunk.into()
+            } else {
+                let mut data = std::mem::take(&mut self.utf8_pending);
+                data.extend_from_slice(chunk);
+                data.into()
+            };
+            match std::str::from_utf8(&data) {
+                Ok(_) => {}
+                Err(e) if e.error_len().is_none() => {
+                    self.utf8_pending = data[e.valid_up_to()..].to_vec();
+                }
+                Err(_) => self.utf8_valid = false,
+            }
+        }
+        
+        self.detector.feed(chunk, false);
+    }
+    
+    /// 只读取了文件开头时使用: 截断处未完成的多字节字符不影响 UTF-8 判定
+    fn finish_truncated(mut self) -> String {
+        self.utf8_pending.clear();
+        self.finish()
+    }
+    
+    fn finish(mut self) -> String {
+        if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(&self.head) {
+            return encoding.name().to_lowercase();
+        }
+        
+        if self.utf8_valid && self.utf8_pending.is_empty() {
+            return "utf-8".to_string();
+        }
+        
+        self.detector.feed(&[], true);
+        match self.detector.guess_assess(None, false) {
+            (encoding, true) => encoding.name().to_lowercase(),
+            (_, false) => "binary".to_string(),
+        }
+    }
+}
+
+/// 根据文件开头的样本判断是否为二进制内容
+///
+/// 出现空字节，或控制字符 (制表、换行等常见空白除外) 超过 10% 即视为二进制。
+/// 带 UTF-16 BOM 的文本本身包含大量空字节，不据此判定。
+fn looks_binary(sample: &[u8]) -> bool {
+    if sample.is_empty() {
+        return false;
+    }
+    
+    if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(sample) {
+        if encoding != encoding_rs::UTF_8 {
+            return false;
+        }
+    }
+    
+    if sample.contains(&0) {
+        return true;
+    }
+    
+    let suspicious = sample.iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    suspicious * 10 > sample.len()
+}
+
+/// 读取文件开头 8KB 判断是否可能为二进制文件，读取失败时返回 false
+pub fn is_probably_binary<P: AsRef<Path>>(path: P) -> bool {
+    is_probably_binary_in(&StdFileSystem, path)
+}
+
+/// 同 [`is_probably_binary`]，通过给定的文件系统读取
+pub fn is_probably_binary_in<P: AsRef<Path>>(file_system: &dyn FileSystem, path: P) -> bool {
+    let mut sample = Vec::with_capacity(BINARY_SNIFF_SIZE);
+    match file_system.open(path.as_ref()) {
+        Ok(file) => match file.take(BINARY_SNIFF_SIZE as u64).read_to_end(&mut sample) {
+            Ok(_) => looks_binary(&sample),
+            Err(_) => false,
+        },
+        Err(_) => false,
+    }
+}
+
+/// 判断文件头是否为常见图片格式的魔数
+fn is_image_magic(head: &[u8]) -> bool {
+    const SIGNATURES: &[&[u8]] = &[
+        b"\x89PNG\r\n\x1a\n",
+        b"\xff\xd8\xff",
+        b"GIF87a",
+        b"GIF89a",
+        b"II*\0",
+        b"MM\0*",
+    ];
+    // BMP 与 ICO 的魔数过短，还要求内容本身是二进制，避免误判以 "BM" 开头的文本
+    const WEAK_SIGNATURES: &[&[u8]] = &[b"BM", b"\0\0\x01\0"];
+    
+    let is_webp = head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP";
+    is_webp
+        || SIGNATURES.iter().any(|signature| head.starts_with(signature))
+        || (WEAK_SIGNATURES.iter().any(|signature| head.starts_with(signature)) && looks_binary(head))
+}
+
+/// 单次遍历文件得到的内容摘要
+struct ContentSummary {
+    lines: usize,
+    line_ending: LineEnding,
+    language: Language,
+    line_breakdown: LineBreakdown,
+    checksum: String,
+    encoding: String,
+    is_binary: bool,
+    /// 文件开头最多 `BINARY_SNIFF_SIZE` 字节
+    head: Vec<u8>,
+}
+
+/// 逐块累积内容摘要 (行数、行分类、编码与文件头)，校验和由调用方单独计算
+///
+/// 语言与是否需要先解码要看文件头才能确定: 文件头读满 (或数据结束) 之前的内容暂存在
+/// `head` 中，之后的块直接逐行统计。
+struct ContentAnalyzer<'a> {
+    path: &'a Path,
+    /// 已知的语言，为 `None` 时根据路径与文件头检测
+    language: Option<Language>,
+    sniffer: EncodingSniffer,
+    head: Vec<u8>,
+    lines: Option<LineStatistics>,
+}
+
+impl<'a> ContentAnalyzer<'a> {
+    fn new(path: &'a Path, language: Option<Language>) -> Self {
+        Self {
+            path,
+            language,
+            sniffer: EncodingSniffer::new(),
+            head: Vec::with_capacity(BINARY_SNIFF_SIZE),
+            lines: None,
+        }
+    }
+    
+    fn feed(&mut self, chunk: &[u8]) {
+        self.sniffer.feed(chunk);
+        
+        if let Some(lines) = self.lines.as_mut() {
+            lines.feed(chunk, false);
+            return;
+        }
+        
+        let take = (BINARY_SNIFF_SIZE - self.head.len()).min(chunk.len());
+        self.head.extend_from_slice(&chunk[..take]);
+        if self.head.len() == BINARY_SNIFF_SIZE {
+            let lines = self.lines.insert(self.start_lines());
+            lines.feed(&chunk[take..], false);
+        }
+    }
+    
+    /// 根据文件头确定语言与解码方式，并统计文件头本身
+    fn start_lines(&self) -> LineStatistics {
+        let is_binary = looks_binary(&self.head);
+        let mut decoder = encoding_rs::Encoding::for_bom(&self.head)
+            .map(|(encoding, _)| encoding)
+            .filter(|encoding| !is_binary && !encoding.is_ascii_compatible())
+            .map(|encoding| encoding.new_decoder_with_bom_removal());
+        
+        let mut decoded = String::new();
+        if let Some(decoder) = decoder.as_mut() {
+            decode_chunk(decoder, &self.head, &mut decoded, false);
+        }
+        
+        let language = self.language.unwrap_or_else(|| {
+            if is_binary {
+                most_likely_language(self.path, "")
+            } else if decoder.is_some() {
+                most_likely_language(self.path, &decoded)
+            } else {
+                most_likely_language(self.path, &String::from_utf8_lossy(&self.head))
+            }
+        });
+        
+        let mut lines = LineStatistics {
+            counter: LineCounter::default(),
+            breakdown: (!is_binary).then(|| BreakdownCounter::new(language)),
+            language,
+            decoder,
+            decoded: String::new(),
+        };
+        if lines.decoder.is_some() {
+            lines.count(decoded.as_bytes());
+        } else {
+            lines.count(&self.head);
+        }
+        lines
+    }
+    
+    fn finish(mut self, checksum: String) -> ContentSummary {
+        let mut lines = match self.lines.take() {
+            Some(lines) => lines,
+            None => self.start_lines(),
+        };
+        lines.feed(&[], true);
+        
+        let is_binary = looks_binary(&self.head);
+        ContentSummary {
+            lines: lines.counter.finish(),
+            line_ending: lines.counter.line_ending(),
+            language: lines.language,
+            line_breakdown: lines.breakdown.map(BreakdownCounter::finish).unwrap_or_default(),
+            checksum,
+            encoding: if is_binary { "binary".to_string() } else { self.sniffer.finish() },
+            is_binary,
+            head: self.head,
+        }
+    }
+}
+
+/// 文件头确定之后的逐行统计
+struct LineStatistics {
+    language: Language,
+    counter: LineCounter,
+    /// 二进制内容不做行分类
+    breakdown: Option<BreakdownCounter>,
+    /// UTF-16 等编码的换行符不是单字节，先解码再统计，BOM 不计入内容
+    decoder: Option<encoding_rs::Decoder>,
+    decoded: String,
+}
+
+impl LineStatistics {
+    fn feed(&mut self, chunk: &[u8], last: bool) {
+        match self.decoder.as_mut() {
+            Some(decoder) => {
+                let mut decoded = std::mem::take(&mut self.decoded);
+                decoded.clear();
+                decode_chunk(decoder, chunk, &mut decoded, last);
+                self.count(decoded.as_bytes());
+                self.decoded = decoded;
+            }
+            None => self.count(chunk),
+        }
+    }
+    
+    fn count(&mut self, text: &[u8]) {
+        self.counter.feed(text);
+        if let Some(breakdown) = self.breakdown.as_mut() {
+            breakdown.feed(text);
+        }
+    }
+}
+
+/// 把一块数据解码后追加到 `text`
+fn decode_chunk(decoder: &mut encoding_rs::Decoder, chunk: &[u8], text: &mut String, last: bool) {
+    if let Some(needed) = decoder.max_utf8_buffer_length(chunk.len()) {
+        text.reserve(needed);
+    }
+    let _ = decoder.decode_to_string(chunk, text, last);
+}
+
+/// 单次遍历数据源，同时计算行数、行分类、校验和并检测编码
+///
+/// `language` 为 `None` 时根据 `path` 与文件头检测；`algorithm` 为 `None` 时跳过校验和计算，
+/// `checksum` 留空。
+fn analyze_reader<R: Read>(
+    reader: R,
+    path: &Path,
+    language: Option<Language>,
+    algorithm: Option<&ChecksumAlgorithm>,
+) -> Result<ContentSummary> {
+    let mut analyzer = ContentAnalyzer::new(path, language);
+    let mut hasher = algorithm.map(ChecksumHasher::new).transpose()?;
+    
+    read_chunks(reader, |chunk| {
+        analyzer.feed(chunk);
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(chunk);
+        }
+    })?;
+    
+    Ok(analyzer.finish(hasher.map(ChecksumHasher::finish).unwrap_or_default()))
+}
+
+/// 读取数据时把每块内容转交给回调的读取器
+struct TeeReader<R, F> {
+    inner: R,
+    sink: F,
+}
+
+impl<R: Read, F: FnMut(&[u8])> Read for TeeReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        (self.sink)(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// 与 `analyze_reader` 相同，但校验和由 `checksummer` 计算: 它读取的每块内容同时用于统计，
+/// 它没有读完的部分随后补读，数据源只遍历一次
+fn analyze_with_checksummer<R: Read>(
+    reader: R,
+    path: &Path,
+    checksummer: &dyn Checksummer,
+) -> Result<ContentSummary> {
+    let mut analyzer = ContentAnalyzer::new(path, None);
+    let mut tee = TeeReader { inner: reader, sink: |chunk: &[u8]| analyzer.feed(chunk) };
+    let checksum = checksummer.hash_reader(&mut tee)?;
+    read_chunks(tee, |_| {})?;
+    Ok(analyzer.finish(checksum))
+}
+
+/// 需要先解码才能按换行符统计行的编码 (UTF-16 等非 ASCII 兼容编码)
+fn text_decoding(encoding: &str) -> Option<&'static encoding_rs::Encoding> {
+    encoding_rs::Encoding::for_label(encoding.as_bytes())
+        .filter(|encoding| !encoding.is_ascii_compatible())
+}
+
+/// 文本开头最多 `max_chars` 个字符
+fn char_prefix(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// 按语言的注释语法逐块统计代码行、注释行与空白行
+///
+/// 不识别字符串字面量中的注释标记；没有注释语法的语言所有非空行都计为代码。
+struct BreakdownCounter {
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    /// 未闭合的块注释的结束标记
+    in_block: Option<&'static str>,
+    /// 上一块末尾尚未结束的行
+    pending: Vec<u8>,
+    breakdown: LineBreakdown,
+}
+
+impl BreakdownCounter {
+    fn new(language: Language) -> Self {
+        Self {
+            line_comment: language.line_comment(),
+            block_comment: language.block_comment(),
+            in_block: None,
+            pending: Vec::new(),
+            breakdown: LineBreakdown::default(),
+        }
+    }
+    
+    fn feed(&mut self, mut chunk: &[u8]) {
+        while let Some(i) = chunk.iter().position(|&b| b == b'\n') {
+            if self.pending.is_empty() {
+                self.classify(&chunk[..=i]);
+            } else {
+                let mut line = std::mem::take(&mut self.pending);
+                line.extend_from_slice(&chunk[..=i]);
+                self.classify(&line);
+                line.clear();
+                self.pending = line;
+            }
+            chunk = &chunk[i + 1..];
+        }
+        self.pending.extend_from_slice(chunk);
+    }
+    
+    fn finish(mut self) -> LineBreakdown {
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            self.classify(&line);
+        }
+        self.breakdown
+    }
+    
+    fn classify(&mut self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        // UTF-8 BOM 不是空白字符，不去掉会让首行注释被当作代码
+        let mut rest = line.trim_start_matches('\u{feff}').trim();
+        if rest.is_empty() && self.in_block.is_none() {
+            self.breakdown.blank += 1;
+            return;
+        }
+        
+        let mut has_code = false;
+        let mut has_comment = false;
+        
+        loop {
+            if let Some(end) = self.in_block {
+                has_comment = true;
+                match rest.find(end) {
+                    Some(i) => {
+                        rest = rest[i + end.len()..].trim_start();
+                        self.in_block = None;
+                    }
+                    None => break,
+                }
+                continue;
+            }
+            
+            // 找到最近的注释起点，之前的内容都是代码
+            let line_start = self.line_comment.and_then(|marker| rest.find(marker));
+            let block_start = self.block_comment.and_then(|(start, _)| rest.find(start).map(|i| (i, start)));
+            
+            match (line_start, block_start) {
+                (Some(i), block) if block.is_none_or(|(j, _)| i <= j) => {
+                    has_code |= !rest[..i].trim().is_empty();
+                    has_comment = true;
+                    break;
+                }
+                (_, Some((j, start))) => {
+                    has_code |= !rest[..j].trim().is_empty();
+                    rest = &rest[j + start.len()..];
+                    self.in_block = self.block_comment.map(|(_, end)| end);
+                }
+                _ => {
+                    has_code |= !rest.is_empty();
+                    break;
+                }
+            }
+        }
+        
+        if has_code {
+            self.breakdown.code += 1;
+        } else if has_comment {
+            self.breakdown.comment += 1;
+        } else {
+            self.breakdown.blank += 1;
+        }
+    }
+}
+
+/// 行分隔的 JSON-RPC 2.0 服务端，供非 Rust 编辑器以子进程方式驱动
+///
+/// 每行一个请求，每个请求对应一行响应 (没有 `id` 的通知除外)。支持的方法: `create_project`、`list_projects`、
+/// `scan_project`、`search_files`、`get_statistics`、`highlight` 与 `shutdown`。
+pub mod server {
+    use super::*;
+    use std::io::Write;
+    
+    // JSON-RPC 标准错误码
+    const PARSE_ERROR: i64 = -32700;
+    const INVALID_REQUEST: i64 = -32600;
+    const METHOD_NOT_FOUND: i64 = -32601;
+    const INVALID_PARAMS: i64 = -32602;
+    /// 引擎返回的错误，`data.code` 为 [`ChangoEditorError::code`]
+    const ENGINE_ERROR: i64 = -32000;
+    
+    #[derive(Debug, Deserialize)]
+    struct Request {
+        method: String,
+        #[serde(default)]
+        params: serde_json::Value,
+    }
+    
+    #[derive(Debug, Serialize)]
+    struct RpcError {
+        code: i64,
+        message: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        data: Option<serde_json::Value>,
+    }
+    
+    impl RpcError {
+        fn new<S: Into<String>>(code: i64, message: S) -> Self {
+            Self { code, message: message.into(), data: None }
+        }
+    }
+    
+    impl From<ChangoEditorError> for RpcError {
+        fn from(err: ChangoEditorError) -> Self {
+            Self {
+                code: ENGINE_ERROR,
+                message: err.to_string(),
+                data: Some(serde_json::json!({ "code": err.code() })),
+            }
+        }
+    }
+    
+    #[derive(Debug, Deserialize)]
+    struct CreateProjectParams {
+        name: String,
+        #[serde(default)]
+        description: String,
+        path: PathBuf,
+    }
+    
+    #[derive(Debug, Deserialize)]
+    struct ProjectParams {
+        project_id: Uuid,
+    }
+    
+    #[derive(Debug, Deserialize)]
+    struct SearchParams {
+        project_id: Uuid,
+        query: String,
+        #[serde(default)]
+        mode: SearchMode,
+    }
+    
+    #[derive(Debug, Deserialize)]
+    struct HighlightParams {
+        code: String,
+        language: Language,
+    }
+    
+    #[derive(Debug, Serialize)]
+    struct ScanResult {
+        indexed: usize,
+        /// 被跳过的文件与带错误代码的原因
+        skipped: Vec<(PathBuf, String)>,
+    }
+    
+    type RpcResult = std::result::Result<serde_json::Value, RpcError>;
+    
+    /// JSON-RPC 服务端，持有一个项目管理器
+    pub struct Server {
+        manager: ProjectManager,
+        highlighter: SimpleSyntaxHighlighter,
+    }
+    
+    impl Server {
+        pub fn new() -> Result<Self> {
+            Self::with_manager(ProjectManager::new())
+        }
+        
+        /// 使用已有的项目管理器 (如从文件加载的) 创建服务端
+        pub fn with_manager(manager: ProjectManager) -> Result<Self> {
+            Ok(Self {
+                manager,
+                highlighter: SimpleSyntaxHighlighter::new()?,
+            })
+        }
+        
+        /// 在标准输入输出上提供服务，直到输入结束或收到 `shutdown`
+        pub fn serve_stdio(&self) -> Result<()> {
+            self.serve(io::stdin().lock(), io::stdout().lock())
+        }
+        
+        /// 逐行读取请求并写出响应，每个响应写出后立即刷新
+        pub fn serve<R: BufRead, W: Write>(&self, reader: R, mut writer: W) -> Result<()> {
+            for line in reader.lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                
+                let (response, shutdown) = self.handle_line(&line);
+                if let Some(response) = response {
+                    writeln!(writer, "{}", response)?;
+                    writer.flush()?;
+                }
+                if shutdown {
+                    break;
+                }
+            }
+            Ok(())
+        }
+        
+        /// 处理一行请求，返回响应文本以及是否收到 `shutdown`
+        ///
+        /// 没有 `id` 成员的请求是通知，照常执行但不返回响应；无法解析的请求总是返回错误响应。
+        pub fn handle_line(&self, line: &str) -> (Option<String>, bool) {
+            let value: serde_json::Value = match serde_json::from_str(line) {
+                Ok(value) => value,
+                Err(e) => {
+                    let error = RpcError::new(PARSE_ERROR, e.to_string());
+                    return (Some(response(serde_json::Value::Null, Err(error))), false);
+                }
+            };
+            
+            let id = value.get("id").cloned();
+            let request: Request = match serde_json::from_value(value) {
+                Ok(request) => request,
+                Err(e) => {
+                    let error = RpcError::new(INVALID_REQUEST, e.to_string());
+                    return (Some(response(id.unwrap_or_default(), Err(error))), false);
+                }
+            };
+            
+            let shutdown = request.method == "shutdown";
+            let result = self.dispatch(&request.method, request.params);
+            (id.map(|id| response(id, result)), shutdown)
+        }
+        
+        fn dispatch(&self, method: &str, params: serde_json::Value) -> RpcResult {
+            match method {
+                "create_project" => {
+                    let params: CreateProjectParams = parse_params(params)?;
+                    let project = self.manager.create_project(params.name, params.description, params.path)?;
+                    to_value(project.snapshot())
+                }
+                "list_projects" => {
+                    let snapshots: Vec<ProjectSnapshot> = self.manager.get_all_projects()
+                        .iter()
+                        .map(|project| project.snapshot())
+                        .collect();
+                    to_value(snapshots)
+                }
+                "scan_project" => {
+                    let params: ProjectParams = parse_params(params)?;
+                    let report = self.project(params.project_id)?.scan_files()?;
+                    to_value(ScanResult {
+                        indexed: report.indexed,
+                        skipped: report.skipped
+                            .into_iter()
+                            .map(|(path, err)| (path, err.to_owned_string()))
+                            .collect(),
+                    })
+                }
+                "search_files" => {
+                    let params: SearchParams = parse_params(params)?;
+                    let project = self.project(params.project_id)?;
+                    to_value(project.search_files_with(&params.query, params.mode))
+                }
+                "get_statistics" => {
+                    let params: ProjectParams = parse_params(params)?;
+                    to_value(self.project(params.project_id)?.get_statistics())
+                }
+                "highlight" => {
+                    let params: HighlightParams = parse_params(params)?;
+                    to_value(self.highlighter.highlight(&params.code, params.language)?)
+                }
+                "shutdown" => Ok(serde_json::Value::Null),
+                _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("未知方法: {}", method))),
+            }
+        }
+        
+        fn project(&self, id: Uuid) -> Result<Arc<Project>> {
+            self.manager.get_project(&id).ok_or(ChangoEditorError::ProjectNotFound(id))
+        }
+    }
+    
+    fn parse_params<T: serde::de::DeserializeOwned>(params: serde_json::Value) -> std::result::Result<T, RpcError> {
+        serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+    }
+    
+    fn to_value<T: Serialize>(value: T) -> RpcResult {
+        serde_json::to_value(value).map_err(|e| ChangoEditorError::from(e).into())
+    }
+    
+    fn response(id: serde_json::Value, result: RpcResult) -> String {
+        let body = match result {
+            Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+        };
+        body.to_string()
+    }
+}
+
+/// WebSocket 扫描进度服务，供 Web 界面实时显示扫描进度
+///
+/// 客户端发送 `{"project_id": "..."}` 订阅一次扫描，服务端以文本帧逐个推送 [`ScanEvent`] 的 JSON；
+/// 同一连接可以依次订阅多次。
+pub mod websocket {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::{self, Message};
+    
+    #[derive(Debug, Deserialize)]
+    struct Subscribe {
+        project_id: Uuid,
+    }
+    
+    pub struct ScanProgressServer {
+        service: Arc<AsyncFileService>,
+    }
+    
+    impl ScanProgressServer {
+        pub fn new(service: Arc<AsyncFileService>) -> Self {
+            Self { service }
+        }
+        
+        /// 在已绑定的监听器上接受连接，每个连接在独立任务中处理
+        pub async fn serve(self: Arc<Self>, listener: tokio::net::TcpListener) -> Result<()> {
+            loop {
+                let (stream, peer) = listener.accept().await?;
+                let server = self.clone();
+                
+                tokio::spawn(async move {
+                    if let Err(e) = server.handle_connection(stream).await {
+                        log::warn!("WebSocket 连接 {} 异常结束: {}", peer, e);
+                    }
+                });
+            }
+        }
+        
+        async fn handle_connection(&self, stream: tokio::net::TcpStream) -> Result<()> {
+            let mut ws = tokio_tungstenite::accept_async(stream).await.map_err(ws_error)?;
+            
+            while let Some(message) = ws.next().await {
+                let text = match message.map_err(ws_error)? {
+                    Message::Text(text) => text,
+                    Message::Close(_) => break,
+                    _ => continue,
+                };
+                
+                match serde_json::from_str::<Subscribe>(&text) {
+                    Ok(subscribe) => {
+                        let mut events = std::pin::pin!(self.service.scan_project_events(subscribe.project_id));
+                        while let Some(event) = events.next().await {
+                            ws.send(to_message(&event)?).await.map_err(ws_error)?;
+                        }
+                    }
+                    Err(e) => {
+                        let event = ScanEvent::error(None, &ChangoEditorError::from(e));
+                        ws.send(to_message(&event)?).await.map_err(ws_error)?;
+                    }
+                }
+            }
+            
+            Ok(())
+        }
+    }
+    
+    fn to_message(event: &ScanEvent) -> Result<Message> {
+        Ok(Message::Text(serde_json::to_string(event)?))
+    }
+    
+    fn ws_error(err: tungstenite::Error) -> ChangoEditorError {
+        ChangoEditorError::IoError(io::Error::other(err))
+    }
+}
+
+/// 性能基准测试
+pub fn benchmark_file_processing() -> Result<()> {
+    println!("=== 性能基准测试 ===");
+    
+    // 创建测试项目
+    let temp_dir = std::env::temp_dir().join("chango_editor_benchmark");
+    fs::create_dir_all(&temp_dir)?;
+    
+    let pm = ProjectManager::new();
+    let project = pm.create_project(
+        "基准测试项目",
+        "性能测试项目",
+        &temp_dir,
+    )?;
+    
+    // 生成测试文件
+    let test_files = generate_test_files(&temp_dir, 100)?;
+    
+    // 添加文件到项目
+    for file_path in test_files {
+        if let Ok(file_info) = FileInfo::from_path(&file_path) {
+            project.add_file(file_info)?;
+        }
+    }
+    
+    // 基准测试：文件扫描
+    let start = Instant::now();
+    let file_count = project.scan_files()?.indexed;
+    let scan_duration = start.elapsed();
+    
+    println!("扫描 {} 个文件耗时: {:?}", file_count, scan_duration);
+    
+    // 基准测试：文件搜索
+    let start = Instant::now();
+    let results = project.search_files("test");
+    let search_duration = start.elapsed();
+    
+    println!("搜索找到 {} 个文件，耗时: {:?}", results.len(), search_duration);
+    
+    // 基准测试：并发处理
+    let files = project.get_all_files();
+    let formatter = CodeFormatter;
+    let processor = ConcurrentFileProcessor::with_default_parallelism(formatter);
+    
+    let start = Instant::now();
+    let results = processor.process_files(files)?;
+    let process_duration = start.elapsed();
+    
+    let success_count = results.iter().filter(|r| r.is_ok()).count();
+    println!("并发处理 {} 个文件成功，耗时: {:?}", success_count, process_duration);
+    
+    // 清理测试文件
+    fs::remove_dir_all(&temp_dir)?;
+    
+    Ok(())
+}
+
+/// 扫描并行化基准测试：对比串行与 rayon 并行构建 FileInfo
+pub fn benchmark_scan_parallelism(file_count: usize) -> Result<()> {
+    println!("=== 扫描并行化基准测试 ({} 个文件) ===", file_count);
+    
+    let temp_dir = std::env::temp_dir().join("chango_editor_scan_benchmark");
+    fs::create_dir_all(&temp_dir)?;
+    let paths = generate_test_files(&temp_dir, file_count)?;
+    
+    let start = Instant::now();
+    let serial = paths.iter().filter(|path| FileInfo::from_path(path).is_ok()).count();
+    let serial_duration = start.elapsed();
+    
+    let start = Instant::now();
+    let parallel = paths.par_iter().filter(|path| FileInfo::from_path(path).is_ok()).count();
+    let parallel_duration = start.elapsed();
+    
+    let project = Project::new("扫描基准", "扫描并行化基准测试", &temp_dir)?;
+    let start = Instant::now();
+    let report = project.scan_files()?;
+    let scan_duration = start.elapsed();
+    
+    println!("串行: {} 个文件，耗时 {:?}", serial, serial_duration);
+    println!("并行: {} 个文件，耗时 {:?}", parallel, parallel_duration);
+    println!("scan_files: {} 个文件，耗时 {:?}", report.indexed, scan_duration);
+    
+    fs::remove_dir_all(&temp_dir)?;
+    Ok(())
+}
+
+/// 生成测试文件
+fn generate_test_files(dir: &Path, count: usize) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let languages = &[Language::Rust, Language::Python, Language::JavaScript];
+    
+    for i in 0..count {
+        let lang = languages[i % languages.len()];
+        let extension = match lang {
+            Language::Rust => "rs",
+            Language::Python => "py", 
+            Language::JavaScript => "js",
+            _ => "txt",
+        };
+        
+        let filename = format!("test_file_{}.{}", i, extension);
+        let file_path = dir.join(&filename);
+        
+        let content = generate_sample_code(lang, i);
+        fs::write(&file_path, content)?;
+        
+        files.push(file_path);
+    }
+    
+    Ok(files)
+}
+
+/// 生成示例代码
+fn generate_sample_code(language: Language, index: usize) -> String {
+    match language {
+        Language::Rust => format!(
+            "// 测试文件 {}\n\
+            fn main() {{\n\
+                println!(\"Hello from Rust file {}!\");\n\
+                let x = {};\n\
+                let y = x * 2;\n\
+                println!(\"Result: {{}}\", y);\n\
+            }}\n",
+            index, index, index
+        ),
+        Language::Python => format!(
+            "# 测试文件 {}\n\
+            def main():\n\
+                print(\"Hello from Python file {}!\")\n\
+                x = {}\n\
+                y = x * 2\n\
+                print(f\"Result: {{y}}\")\n\
+            \n\
+            if __name__ == \"__main__\":\n\
+                main()\n",
+            index, index, index
+        ),
+        Language::JavaScript => format!(
+            "// 测试文件 {}\n\
+            function main() {{\n\
+                console.log(\"Hello from JavaScript file {}!\");\n\
+                const x = {};\n\
+                const y = x * 2;\n\
+                console.log(`Result: ${{y}}`);\n\
+            }}\n\
+            \n\
+            main();\n",
+            index, index, index
+        ),
+        _ => format!("# 未知语言测试文件 {}\n", index),
+    }
+}
+
+/// 异步主函数演示
+#[tokio::main]
+async fn async_demo() -> Result<()> {
+    println!("=== 异步功能演示 ===");
+    
+    let pm = Arc::new(ProjectManager::new());
+    let service = AsyncFileService::new(pm.clone());
+    
+    // 创建测试项目
+    let temp_dir = std::env::temp_dir().join("chango_editor_async_demo");
+    fs::create_dir_all(&temp_dir)?;
+    
+    let project = pm.create_project(
+        "异步演示项目", 
+        "异步功能测试项目",
+        &temp_dir,
+    )?;
+    
+    // 生成测试文件
+    generate_test_files(&temp_dir, 50)?;
+    
+    // 异步扫描
+    println!("开始异步扫描...");
+    let start = Instant::now();
+    let file_count = service.scan_project_async(project.id, tokio_util::sync::CancellationToken::new()).await?.indexed;
+    let duration = start.elapsed();
+    
+    println!("异步扫描完成: {} 个文件，耗时: {:?}", file_count, duration);
+    
+    // 异步搜索
+    println!("开始异步搜索...");
+    let start = Instant::now();
+    let results = service
+        .search_files_async(project.id, "test".to_string(), tokio_util::sync::CancellationToken::new())
+        .await?;
+    let duration = start.elapsed();
+    
+    println!("异步搜索完成: {} 个结果，耗时: {:?}", results.len(), duration);
+    
+    // 清理
+    fs::remove_dir_all(&temp_dir)?;
+    
+    Ok(())
+}
+
+/// 主函数
+fn main() -> Result<()> {
+    // 默认显示 info 级别日志，可通过 RUST_LOG 调整
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    
+    // 服务模式下标准输出只用于协议响应，不打印启动信息
+    if std::env::args().nth(1).as_deref() == Some("serve") {
+        return server::Server::new()?.serve_stdio();
+    }
+    
+    println!("=== {} v{} ===", APP_NAME, APP_VERSION);
+    println!("启动时间: {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"));
+    
+    // 解析命令行参数
+    let args: Vec<String> = std::env::args().collect();
+    
+    if args.len() < 2 {
+        println!("使用方法:");
+        println!("  {} demo          - 运行功能演示", args[0]);
+        println!("  {} benchmark     - 运行性能基准测试", args[0]);
+        println!("  {} async         - 运行异步功能演示", args[0]);
+        println!("  {} create <name> <path> - 创建项目", args[0]);
+        println!("  {} serve         - 在标准输入输出上提供 JSON-RPC 服务", args[0]);
+        return Ok(());
+    }
+    
+    match args[1].as_str() {
+        "demo" => {
+            // 功能演示
+            println!("\n=== 功能演示 ===");
+            
+            let pm = ProjectManager::new();
+            
+            // 创建示例项目
+            let project = pm.create_project(
+                "Rust示例项目",
+                "展示Rust语言特性",
+                ".",
+            )?;
+            
+            // 扫描文件
+            let report = project.scan_files()?;
+            println!("扫描到 {} 个文件", report.indexed);
+            for (path, e) in &report.skipped {
+                println!("  跳过 {:?}: {}", path, e);
+            }
+            
+            println!("\n文件结构:");
+            print!("{}", project.format_tree_annotated());
+            
+            // 显示统计信息
+            let stats = project.get_statistics();
+            println!("\n项目统计:");
+            println!("  总文件数: {}", stats.total_files);
+            println!("  总行数: {}", stats.total_lines);
+            println!("  总大小: {} 字节", stats.total_size);
+            
+            println!("\n语言分布:");
+            for (language, lang_stats) in stats.sorted_by_lines() {
+                println!("  {}: {} 个文件, {} 行代码", 
+                    language, lang_stats.file_count, lang_stats.line_count);
+            }
+            
+            // 演示搜索
+            let search_results = project.search_files("test");
+            println!("\n搜索 'test' 找到 {} 个文件", search_results.len());
+            
+            // 演示并发处理
+            let files = project.get_all_files();
+            if !files.is_empty() {
+                println!("\n演示并发处理...");
+                let formatter = CodeFormatter;
+                let processor = ConcurrentFileProcessor::with_default_parallelism(formatter);
+                
+                let start = Instant::now();
+                let results = processor.process_files(files.clone())?;
+                let duration = start.elapsed();
+                
+                let success_count = results.iter().filter(|r| r.is_ok()).count();
+                println!("并发处理完成: {}/{} 成功，耗时: {:?}", 
+                    success_count, files.len(), duration);
+            }
+            
+            // 演示语法高亮
+            if let Ok(highlighter) = SimpleSyntaxHighlighter::new() {
+                let sample_code = "fn main() { let x = 42; println!(\"Hello, Rust!\"); }";
+                let highlighted = highlighter.highlight(sample_code, Language::Rust)?;
+                println!("\n语法高亮演示:");
+                println!("原代码: {}", sample_code);
+                println!("高亮后: {}", highlighted);
+            }
+        }
+        
+        "benchmark" => {
+            benchmark_file_processing()?;
+            benchmark_scan_parallelism(3000)?;
+        }
+        
+        "async" => {
+            // 运行异步演示
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(async_demo())?;
+        }
+        
+        "create" => {
+            if args.len() < 4 {
+                println!("使用方法: {} create <项目名> <项目路径>", args[0]);
+                return Ok(());
+            }
+            
+            let name = &args[2];
+            let path = &args[3];
+            
+            let pm = ProjectManager::new();
+            let project = pm.create_project(name, "通过命令行创建", path)?;
+            
+            println!("项目创建成功: {} (ID: {})", project.name, project.id);
+            
+            let report = project.scan_files()?;
+            println!("扫描到 {} 个文件", report.indexed);
+            for (path, e) in &report.skipped {
+                println!("  跳过 {:?}: {}", path, e);
+            }
+        }
+        
+        _ => {
+            println!("未知命令: {}", args[1]);
+            return Ok(());
+        }
+    }
+    
+    println!("\n程序执行完成!");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// 临时文件或目录，离开作用域时 (包括断言失败) 自动删除
+    struct TempPath(PathBuf);
+    
+    impl std::ops::Deref for TempPath {
+        type Target = Path;
+        
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+    
+    impl AsRef<Path> for TempPath {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+    
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = if self.0.is_dir() {
+                fs::remove_dir_all(&self.0)
+            } else {
+                fs::remove_file(&self.0)
+            };
+        }
+    }
+    
+    /// 在临时目录中写入一个内容确定的文件，`extension` 为空或带前导点 (如 ".rs")
+    fn temp_file(extension: &str, content: &[u8]) -> TempPath {
+        let path = std::env::temp_dir().join(format!("chango_test_{}{}", Uuid::new_v4(), extension));
+        fs::write(&path, content).unwrap();
+        TempPath(path)
+    }
+    
+    /// 创建一个空的临时目录
+    fn temp_dir() -> TempPath {
+        let path = std::env::temp_dir().join(format!("chango_test_{}", Uuid::new_v4()));
+        fs::create_dir_all(&path).unwrap();
+        TempPath(path)
+    }
+    
+    #[test]
+    fn file_lines_empty_file_is_zero() {
+        let path = temp_file("", b"");
+        assert_eq!(FileInfo::from_path(&path).unwrap().lines, 0);
+    }
+    
+    #[test]
+    fn file_lines_counts_unterminated_last_line() {
+        let path = temp_file("", b"one\ntwo\nthree");
+        assert_eq!(FileInfo::from_path(&path).unwrap().lines, 3);
+        
+        let path = temp_file("", b"one\ntwo\nthree\n");
+        assert_eq!(FileInfo::from_path(&path).unwrap().lines, 3);
+    }
+    
+    #[test]
+    fn file_lines_handles_crlf() {
+        let path = temp_file("", b"one\r\ntwo\r\nthree");
+        assert_eq!(FileInfo::from_path(&path).unwrap().lines, 3);
+        
+        let path = temp_file("", b"one\r\ntwo\r\n");
+        assert_eq!(FileInfo::from_path(&path).unwrap().lines, 2);
+    }
+    
+    #[test]
+    fn highlight_escapes_surrounding_code() {
+        let highlighter = SimpleSyntaxHighlighter::new().unwrap();
+        let highlighted = highlighter
+            .highlight("let ok = a < b && c > d;", Language::Rust)
+            .unwrap();
+        
+        assert_eq!(
+            highlighted,
+            "<keyword>let</keyword> ok = a &lt; b &amp;&amp; c &gt; d;"
+        );
+    }
+    
+    #[test]
+    fn highlight_does_not_double_escape() {
+        let highlighter = SimpleSyntaxHighlighter::new().unwrap();
+        let highlighted = highlighter
+            .highlight("if x == \"&amp;\" { return; }", Language::Rust)
+            .unwrap();
+        
+        assert_eq!(
+            highlighted,
+            "<keyword>if</keyword> x == &quot;&amp;amp;&quot; { <keyword>return</keyword>; }"
+        );
+    }
+    
+    #[test]
+    fn highlight_supports_typescript() {
+        let highlighter = SimpleSyntaxHighlighter::new().unwrap();
+        let highlighted = highlighter.highlight("const x = 1;", Language::TypeScript).unwrap();
+        
+        assert_eq!(highlighted, "<keyword>const</keyword> x = 1;");
+    }
+    
+    #[test]
+    fn tokenize_covers_source_on_char_boundaries() {
+        let highlighter = SimpleSyntaxHighlighter::new().unwrap();
+        let code = "fn 主函数<'a>(x: &'a str) { let c = '字'; // 注释\n}";
+        let tokens = highlighter.tokenize(code, Language::Rust).unwrap();
+        
+        let mut pos = 0;
+        for token in &tokens {
+            assert_eq!(token.start, pos);
+            assert!(code.is_char_boundary(token.start) && code.is_char_boundary(token.end));
+            pos = token.end;
+        }
+        assert_eq!(pos, code.len());
+        
+        let kinds: Vec<(TokenKind, &str)> = tokens.iter()
+            .filter(|t| t.kind != TokenKind::Whitespace && t.kind != TokenKind::Punctuation)
+            .map(|t| (t.kind, t.text(code)))
+            .collect();
+        assert_eq!(kinds, vec![
+            (TokenKind::Keyword, "fn"),
+            (TokenKind::Identifier, "主函数"),
+            (TokenKind::Identifier, "a"),
+            (TokenKind::Identifier, "x"),
+            (TokenKind::Identifier, "a"),
+            (TokenKind::Identifier, "str"),
+            (TokenKind::Keyword, "let"),
+            (TokenKind::Identifier, "c"),
+            (TokenKind::String, "'字'"),
+            (TokenKind::Comment, "// 注释"),
+        ]);
+    }
+    
+    #[test]
+    fn highlight_escapes_unsupported_languages() {
+        let highlighter = SimpleSyntaxHighlighter::new().unwrap();
+        let highlighted = highlighter.highlight("<a>", Language::Unknown).unwrap();
+        
+        assert_eq!(highlighted, "&lt;a&gt;");
+    }
+    
+    #[test]
+    fn normalize_line_endings_rejects_utf16() {
+        let dir = temp_dir();
+        let path = dir.join("wide.rs");
+        let content = utf16le("a\r\nb\r\n");
+        fs::write(&path, &content).unwrap();
+        let project = Project::new("wide", "", &dir).unwrap();
+        project.scan_files().unwrap();
+        let id = project.file_id(&path);
+        
+        let result = project.normalize_line_endings(&id, LineEnding::Lf);
+        assert!(matches!(result, Err(ChangoEditorError::ValidationError(_))), "{:?}", result);
+        assert_eq!(fs::read(&path).unwrap(), content);
+    }
+    
+    #[test]
+    fn normalize_line_endings_checks_the_file_not_the_index() {
+        let dir = temp_dir();
+        let path = dir.join("a.rs");
+        fs::write(&path, "a\nb\n").unwrap();
+        let project = Project::new("endings", "", &dir).unwrap();
+        project.scan_files().unwrap();
+        let id = project.file_id(&path);
+        
+        // 索引仍记录为 LF，磁盘上已改为 CRLF
+        fs::write(&path, "a\r\nb\r\n").unwrap();
+        project.normalize_line_endings(&id, LineEnding::Lf).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\nb\n");
+        
+        project.normalize_line_endings(&id, LineEnding::CrLf).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\r\nb\r\n");
+        let file_info = project.get_file(&id).unwrap();
+        assert_eq!(file_info.line_ending, LineEnding::CrLf);
+        assert_eq!(file_info.size, 6);
+        assert!(project.verify_checksum(&id).unwrap());
+    }
+    
+    #[test]
+    fn watcher_events_are_rebased_onto_project_root() {
+        let event = FileEvent::Renamed {
+            from: PathBuf::from("/home/user/proj/src/a.rs"),
+            to: PathBuf::from("/home/user/proj/src/b.rs"),
+        };
+        assert_eq!(
+            event.rebase(Path::new("/home/user/proj"), Path::new("./proj")),
+            FileEvent::Renamed {
+                from: PathBuf::from("./proj/src/a.rs"),
+                to: PathBuf::from("./proj/src/b.rs"),
+            }
+        );
+        
+        let outside = FileEvent::Modified(PathBuf::from("/elsewhere/a.rs"));
+        assert_eq!(outside.clone().rebase(Path::new("/home/user/proj"), Path::new("proj")), outside);
+    }
+    
+    #[test]
+    fn comment_syntax_per_language() {
+        assert_eq!(Language::Rust.line_comment(), Some("//"));
+        assert_eq!(Language::JavaScript.line_comment(), Some("//"));
+        assert_eq!(Language::Python.line_comment(), Some("#"));
+        assert_eq!(Language::Ruby.line_comment(), Some("#"));
+        assert_eq!(Language::Unknown.line_comment(), None);
+        
+        assert_eq!(Language::Rust.block_comment(), Some(("/*", "*/")));
+        assert_eq!(Language::Cpp.block_comment(), Some(("/*", "*/")));
+        assert_eq!(Language::Python.block_comment(), None);
+        assert_eq!(Language::Unknown.block_comment(), None);
+    }
+    
+    #[test]
+    fn edit_history_undo_redo_round_trip() {
+        let mut buffer = String::from("hello");
+        let mut history = EditHistory::new(10);
+        
+        history.insert(&mut buffer, 5, " world").unwrap();
+        history.delete(&mut buffer, 0..1).unwrap();
+        assert_eq!(buffer, "ello world");
+        
+        assert!(history.undo(&mut buffer));
+        assert_eq!(buffer, "hello world");
+        assert!(history.undo(&mut buffer));
+        assert_eq!(buffer, "hello");
+        assert!(!history.undo(&mut buffer));
+        
+        assert!(history.redo(&mut buffer));
+        assert!(history.redo(&mut buffer));
+        assert_eq!(buffer, "ello world");
+        assert!(!history.redo(&mut buffer));
+    }
+    
+    #[test]
+    fn edit_history_coalesces_typing() {
+        let mut buffer = String::new();
+        let mut history = EditHistory::new(10);
+        
+        for (i, c) in "abc".chars().enumerate() {
+            history.insert(&mut buffer, i, &c.to_string()).unwrap();
+        }
+        history.insert(&mut buffer, 3, "\n").unwrap();
+        history.insert(&mut buffer, 4, "d").unwrap();
+        assert_eq!(buffer, "abc\nd");
+        
+        // 换行结束合并: "abc\n" 为一步，"d" 为另一步
+        assert!(history.undo(&mut buffer));
+        assert_eq!(buffer, "abc\n");
+        assert!(history.undo(&mut buffer));
+        assert_eq!(buffer, "");
+        assert!(!history.can_undo());
+    }
+    
+    #[test]
+    fn edit_history_respects_max_depth_and_clears_redo() {
+        let mut buffer = String::new();
+        let mut history = EditHistory::new(2);
+        
+        history.insert(&mut buffer, 0, "aa").unwrap();
+        history.insert(&mut buffer, 2, "bb").unwrap();
+        history.insert(&mut buffer, 4, "cc").unwrap();
+        
+        assert!(history.undo(&mut buffer));
+        assert!(history.undo(&mut buffer));
+        assert!(!history.undo(&mut buffer));
+        assert_eq!(buffer, "aa");
+        
+        history.insert(&mut buffer, 2, "x").unwrap();
+        assert!(!history.can_redo());
+    }
+    
+    #[test]
+    fn edit_history_rejects_invalid_offsets() {
+        let mut buffer = String::from("字");
+        let mut history = EditHistory::new(10);
+        
+        assert!(matches!(history.insert(&mut buffer, 1, "x"), Err(ChangoEditorError::ValidationError(_))));
+        assert!(matches!(history.delete(&mut buffer, 0..10), Err(ChangoEditorError::ValidationError(_))));
+        assert_eq!(buffer, "字");
+        assert!(!history.can_undo());
+    }
+    
+    /// 断言某种语言的关键字表非空且包含给定的代表性关键字
+    fn assert_keywords(language: Language, expected: &[&str]) {
+        let keywords = language.keywords();
+        assert!(!keywords.is_empty(), "{} 没有关键字", language);
+        for keyword in expected {
+            assert!(keywords.contains(keyword), "{} 缺少关键字 {}", language, keyword);
+        }
+    }
+    
+    #[test]
+    fn go_keywords() {
+        assert_keywords(Language::Go, &["func", "package", "defer", "chan", "go"]);
+    }
+    
+    #[test]
+    fn java_keywords() {
+        assert_keywords(Language::Java, &["class", "public", "synchronized", "extends", "throws"]);
+    }
+    
+    #[test]
+    fn cpp_keywords() {
+        assert_keywords(Language::Cpp, &["template", "namespace", "virtual", "constexpr", "nullptr"]);
+    }
+    
+    #[test]
+    fn csharp_keywords() {
+        assert_keywords(Language::CSharp, &["namespace", "using", "foreach", "override", "async"]);
+    }
+    
+    #[test]
+    fn typescript_keywords_extend_javascript() {
+        assert_keywords(Language::TypeScript, &["interface", "type", "enum", "namespace", "readonly"]);
+        assert_keywords(Language::TypeScript, Language::JavaScript.keywords());
+    }
+    
+    #[test]
+    fn project_survives_poisoned_lock() {
+        let dir = temp_dir();
+        fs::write(dir.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(dir.join("b.rs"), "fn b() {}\n").unwrap();
+        
+        let project = Arc::new(Project::new("poison", "", &dir).unwrap());
+        project.add_file(FileInfo::from_path(dir.join("a.rs")).unwrap()).unwrap();
+        
+        let writer = project.clone();
+        let result = thread::spawn(move || {
+            let _files = writer.files.write().unwrap();
+            panic!("持有写锁时崩溃");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(project.files.is_poisoned());
+        
+        assert_eq!(project.get_all_files().len(), 1);
+        let id = project.add_file(FileInfo::from_path(dir.join("b.rs")).unwrap()).unwrap();
+        assert_eq!(project.get_all_files().len(), 2);
+        project.remove_file(&id).unwrap();
+        assert_eq!(project.get_statistics().total_files, 1);
+    }
+    
+    #[test]
+    fn scanned_extension_requires_exact_match() {
+        let extensions = ProjectConfig::default().extension_set();
+        assert!(is_scanned_extension(&extensions, Path::new("src/main.rs")));
+        assert!(!is_scanned_extension(&extensions, Path::new("foo.backup_rs")));
+        assert!(!is_scanned_extension(&extensions, Path::new("x.myts")));
+    }
+    
+    #[test]
+    fn scanned_extension_ignores_case() {
+        let config = ProjectConfig {
+            scanned_extensions: vec![".RS".to_string()],
+            ..ProjectConfig::default()
+        };
+        let extensions = config.extension_set();
+        assert!(is_scanned_extension(&extensions, Path::new("Foo.RS")));
+        assert!(is_scanned_extension(&extensions, Path::new("foo.rs")));
+        
+        let extensions = ProjectConfig::default().extension_set();
+        assert!(is_scanned_extension(&extensions, Path::new("Foo.RS")));
+    }
+    
+    /// 编码为带 BOM 的 UTF-16LE
+    fn utf16le(text: &str) -> Vec<u8> {
+        let mut bytes = vec![0xff, 0xfe];
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        bytes
+    }
+    
+    #[test]
+    fn utf16le_lines_are_counted_after_decoding() {
+        let text: String = (1..=10).map(|i| format!("// 第 {} 行\n", i)).collect();
+        let path = temp_file(".rs", &utf16le(&text));
+        
+        let file_info = FileInfo::from_path(&path).unwrap();
+        assert_eq!(file_info.encoding, "utf-16le");
+        assert!(!file_info.is_binary);
+        assert_eq!(file_info.lines, 10);
+        assert_eq!(file_info.line_ending, LineEnding::Lf);
+        assert_eq!(file_info.line_breakdown.comment, 10);
+        
+        let path = temp_file("", &utf16le("a\r\nb\r\nc"));
+        let file_info = FileInfo::from_path(&path).unwrap();
+        assert_eq!(file_info.lines, 3);
+        assert_eq!(file_info.line_ending, LineEnding::CrLf);
+    }
+    
+    #[test]
+    fn utf8_bom_does_not_hide_leading_comment() {
+        let path = temp_file(".rs", b"\xef\xbb\xbf// comment\nfn main() {}\n");
+        
+        let file_info = FileInfo::from_path(&path).unwrap();
+        assert_eq!(file_info.lines, 2);
+        assert_eq!(file_info.line_breakdown.comment, 1);
+        assert_eq!(file_info.line_breakdown.code, 1);
+    }
+    
+    #[test]
+    fn language_serde_round_trips_as_lowercase_id() {
+        for &language in Language::ALL {
+            let json = serde_json::to_string(&language).unwrap();
+            assert_eq!(json, format!("\"{}\"", language.id()));
+            assert_eq!(serde_json::from_str::<Language>(&json).unwrap(), language);
+            assert_eq!(Language::from_id(language.id()), language);
+        }
+        assert_eq!(serde_json::to_string(&Language::CSharp).unwrap(), "\"csharp\"");
+        assert_eq!(serde_json::to_string(&Language::Cpp).unwrap(), "\"cpp\"");
+    }
+    
+    #[test]
+    fn language_deserialize_is_lenient() {
+        assert_eq!(serde_json::from_str::<Language>("\"CSharp\"").unwrap(), Language::CSharp);
+        assert_eq!(serde_json::from_str::<Language>("\"rs\"").unwrap(), Language::Rust);
+        assert_eq!(serde_json::from_str::<Language>("\"cobol\"").unwrap(), Language::Unknown);
+        
+        let mut themes = HashMap::new();
+        themes.insert(Language::Rust, "rust-dark".to_string());
+        let json = serde_json::to_string(&themes).unwrap();
+        assert_eq!(json, r#"{"rust":"rust-dark"}"#);
+        assert_eq!(serde_json::from_str::<HashMap<Language, String>>(&json).unwrap(), themes);
+    }
+    
+    #[test]
+    fn every_commentable_language_has_line_comment() {
+        for language in Language::ALL {
+            if language.block_comment().is_some() {
+                assert!(language.line_comment().is_some(), "{} 缺少行注释", language);
+            }
+        }
+    }
+    
+    #[test]
+    fn line_breakdown_spans_read_chunks() {
+        let mut content = b"/*\n".to_vec();
+        content.extend("x\n".repeat(READ_BUFFER_SIZE).as_bytes());
+        content.extend(b"*/ fn main() {}\n// end");
+        let path = temp_file(".rs", &content);
+        
+        let file_info = FileInfo::from_path(&path).unwrap();
+        assert_eq!(file_info.lines, READ_BUFFER_SIZE + 3);
+        assert_eq!(file_info.line_breakdown.comment, READ_BUFFER_SIZE + 2);
+        assert_eq!(file_info.line_breakdown.code, 1);
+        
+        // 逐字节输入与一次输入结果相同
+        let mut whole = BreakdownCounter::new(Language::Rust);
+        whole.feed(&content);
+        let mut split = BreakdownCounter::new(Language::Rust);
+        for byte in &content {
+            split.feed(std::slice::from_ref(byte));
+        }
+        assert_eq!(split.finish(), whole.finish());
+    }
+    
+    #[test]
+    fn diff_lines_finds_shortest_edit() {
+        let old = ["a", "b", "c", "d"];
+        let new = ["a", "c", "d", "e"];
+        assert_eq!(
+            diff_lines(&old, &new),
+            vec![
+                DiffOp::Equal(0, 0),
+                DiffOp::Delete(1),
+                DiffOp::Equal(2, 1),
+                DiffOp::Equal(3, 2),
+                DiffOp::Insert(3),
+            ]
+        );
+        assert_eq!(diff_lines(&[], &["x"]), vec![DiffOp::Insert(0)]);
+        assert_eq!(diff_lines(&["x"], &[]), vec![DiffOp::Delete(0)]);
+    }
+    
+    #[test]
+    fn unified_diff_renders_hunks_with_context() {
+        let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let modified = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n10\n";
+        assert_eq!(
+            unified_diff(original, modified, Path::new("a.rs")),
+            "--- a.rs\n+++ a.rs\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+    
+    #[test]
+    fn unified_diff_reports_line_ending_only_changes() {
+        assert_eq!(
+            unified_diff("a\nb", "a\nb\n", Path::new("a.rs")),
+            "--- a.rs\n+++ a.rs\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n"
+        );
+        assert_eq!(
+            unified_diff("a\r\n", "a\n", Path::new("a.rs")),
+            "--- a.rs\n+++ a.rs\n@@ -1,1 +1,1 @@\n-a\r\n+a\n"
+        );
+    }
+    
+    #[test]
+    fn unknown_theme_does_not_block_loading_other_projects() {
+        let good = temp_dir();
+        let bad = temp_dir();
+        let manager = ProjectManager::new();
+        let kept = manager.create_project("good", "", &good).unwrap();
+        let config = ProjectConfig::builder().syntax_theme(Language::Go, "solarized").build();
+        let rejected = manager.create_project_with_config("bad", "", &bad, config).unwrap();
+        
+        let state = temp_file(".json", b"");
+        manager.save_to(&state).unwrap();
+        let loaded = ProjectManager::load_from(&state).unwrap();
+        
+        assert!(loaded.get_project(&kept.id).is_some());
+        assert!(loaded.get_project(&rejected.id).is_none());
+        let missing = loaded.missing_projects();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].0.id, rejected.id);
+        assert!(missing[0].1.contains("solarized"), "{}", missing[0].1);
+    }
+    
+    #[test]
+    fn partial_project_config_uses_defaults() {
+        let dir = temp_dir();
+        fs::write(dir.join(PROJECT_CONFIG_FILE), "max_backups = 3\n").unwrap();
+        
+        let project = Project::new("partial", "", &dir).unwrap();
+        let defaults = ProjectConfig::default();
+        assert_eq!(project.config.max_backups, 3);
+        assert_eq!(project.config.auto_save, defaults.auto_save);
+        assert_eq!(project.config.exclude_patterns, defaults.exclude_patterns);
+        assert_eq!(project.config.scanned_extensions, defaults.scanned_extensions);
+        assert_eq!(project.config.generated_patterns, defaults.generated_patterns);
+        assert!(!project.config.include_hidden);
+        
+        let manager = ProjectManager::new();
+        let created = manager.create_project("partial", "", &dir).unwrap();
+        assert_eq!(created.config.max_backups, 3);
+    }
+    
+    #[test]
+    fn text_buffer_line_count_only_splits_on_newline() {
+        let buffer = TextBuffer::from("a\rb\u{2028}c\n");
+        assert_eq!(buffer.line_count(), 1);
+        assert_eq!(buffer.len_lines(), 4);
+        
+        assert_eq!(TextBuffer::new().line_count(), 0);
+        assert_eq!(TextBuffer::from("a\r\nb").line_count(), 2);
+    }
+    
+    #[test]
+    fn fast_read_matches_full_read_without_checksum() {
+        let path = temp_file(".rs", b"// comment\nfn main() {}\n\n");
+        
+        let mut fast = FileInfo::from_path_fast(&path).unwrap();
+        let full = FileInfo::from_path(&path).unwrap();
+        assert!(!fast.has_checksum());
+        assert_eq!(fast.lines, full.lines);
+        assert_eq!(fast.language, full.language);
+        assert_eq!(fast.line_breakdown, full.line_breakdown);
+        
+        fast.ensure_checksum().unwrap();
+        assert_eq!(fast.checksum, full.checksum);
+    }
+    
+    /// 把文件的修改时间设为 UNIX 纪元之后 `secs` 秒
+    fn set_mtime(path: &Path, secs: u64) {
+        fs::File::options().write(true).open(path).unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap();
+    }
+    
+    #[test]
+    fn rescan_with_cache_reports_only_content_changes() {
+        let dir = temp_dir();
+        let path = dir.join("a.rs");
+        fs::write(&path, "fn a() {}\n").unwrap();
+        set_mtime(&path, 1_000_000);
+        
+        let project = Project::new("cache", "", &dir).unwrap();
+        project.scan_files().unwrap();
+        let id = project.find_file_by_path(&path).unwrap().id;
+        
+        // 冷缓存: 以索引中的文件信息为准，未变化的文件不算修改
+        let mut cache = ScanCache::new();
+        let delta = project.rescan_with_cache(&mut cache).unwrap();
+        assert!(delta.is_empty(), "{:?}", delta);
+        assert_eq!(cache.len(), 1);
+        
+        // 修改时间命中
+        let delta = project.rescan_with_cache(&mut cache).unwrap();
+        assert!(delta.is_empty(), "{:?}", delta);
+        
+        // 只改动时间戳: 校验和命中，更新修改时间但不算修改
+        set_mtime(&path, 2_000_000);
+        let delta = project.rescan_with_cache(&mut cache).unwrap();
+        assert!(delta.is_empty(), "{:?}", delta);
+        assert_eq!(project.get_file(&id).unwrap().modified_at, UNIX_EPOCH + Duration::from_secs(2_000_000));
+        
+        // 内容真正变化
+        fs::write(&path, "fn a() {}\nfn b() {}\n").unwrap();
+        set_mtime(&path, 3_000_000);
+        let delta = project.rescan_with_cache(&mut cache).unwrap();
+        assert_eq!(delta.modified, vec![id]);
+        assert_eq!(project.get_file(&id).unwrap().lines, 2);
+    }
+    
+    /// 只读取前 4 字节、返回其长度的校验和替身
+    struct PrefixChecksummer;
+    
+    impl Checksummer for PrefixChecksummer {
+        fn hash_reader(&self, reader: &mut dyn Read) -> Result<String> {
+            let mut prefix = [0u8; 4];
+            let n = reader.read(&mut prefix)?;
+            Ok(format!("prefix-{}", n))
+        }
+    }
+    
+    #[test]
+    fn custom_checksummer_is_labelled_and_not_compared_with_builtin() {
+        let dir = temp_dir();
+        let path = dir.join("a.rs");
+        fs::write(&path, "fn a() {}\nfn b() {}\n").unwrap();
+        
+        let custom = FileInfo::from_path_with_checksummer(&path, &PrefixChecksummer).unwrap();
+        assert_eq!(custom.checksum, "prefix-4");
+        assert!(matches!(&custom.checksum_algorithm, ChecksumAlgorithm::Custom(name) if name.contains("PrefixChecksummer")));
+        // 校验和替身只读了开头，其余统计仍覆盖整个文件
+        assert_eq!(custom.lines, 2);
+        assert_eq!(custom.line_breakdown.code, 2);
+        
+        let builtin = FileInfo::from_path_with_checksummer(&path, &ChecksumAlgorithm::Blake3).unwrap();
+        assert_eq!(builtin.checksum_algorithm, ChecksumAlgorithm::Blake3);
+        assert_eq!(builtin.checksum, FileInfo::from_path_with_algorithm(&path, ChecksumAlgorithm::Blake3).unwrap().checksum);
+        assert_eq!(custom.checksum_matches(&builtin), None);
+        
+        let project = Project::new("custom", "", &dir).unwrap();
+        let id = project.add_file(custom).unwrap();
+        assert!(matches!(project.verify_checksum(&id), Err(ChangoEditorError::ValidationError(_))));
+    }
+    
+    fn rpc(server: &server::Server, line: &str) -> (serde_json::Value, bool) {
+        let (response, shutdown) = server.handle_line(line);
+        (serde_json::from_str(&response.expect("请求应有响应")).unwrap(), shutdown)
+    }
+    
+    #[test]
+    fn server_reports_json_rpc_errors() {
+        let server = server::Server::new().unwrap();
+        
+        let (response, _) = rpc(&server, "{not json");
+        assert_eq!(response["id"], serde_json::Value::Null);
+        assert_eq!(response["error"]["code"], -32700);
+        
+        let (response, _) = rpc(&server, r#"{"jsonrpc": "2.0", "id": 1, "params": {}}"#);
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["error"]["code"], -32600);
+        
+        let (response, _) = rpc(&server, r#"{"jsonrpc": "2.0", "id": 2, "method": "frobnicate"}"#);
+        assert_eq!(response["error"]["code"], -32601);
+        
+        let (response, _) = rpc(&server, r#"{"jsonrpc": "2.0", "id": 3, "method": "get_statistics", "params": {}}"#);
+        assert_eq!(response["error"]["code"], -32602);
+        
+        let request = format!(
+            r#"{{"jsonrpc": "2.0", "id": 4, "method": "scan_project", "params": {{"project_id": "{}"}}}}"#,
+            Uuid::new_v4(),
+        );
+        let (response, _) = rpc(&server, &request);
+        assert_eq!(response["error"]["code"], -32000);
+        assert_eq!(response["error"]["data"]["code"], ChangoEditorError::ProjectNotFound(Uuid::nil()).code());
+    }
+    
+    #[test]
+    fn server_answers_requests_and_skips_notifications() {
+        let server = server::Server::new().unwrap();
+        
+        let (response, shutdown) = rpc(&server, r#"{"jsonrpc": "2.0", "id": "a", "method": "list_projects"}"#);
+        assert_eq!(response["id"], "a");
+        assert_eq!(response["result"], serde_json::json!([]));
+        assert!(!shutdown);
+        
+        // 通知照常执行，但没有响应
+        let (response, shutdown) = server.handle_line(r#"{"jsonrpc": "2.0", "method": "list_projects"}"#);
+        assert!(response.is_none());
+        assert!(!shutdown);
+        
+        let (response, shutdown) = rpc(&server, r#"{"jsonrpc": "2.0", "id": 5, "method": "shutdown"}"#);
+        assert_eq!(response["result"], serde_json::Value::Null);
+        assert!(shutdown);
+        
+        let (response, shutdown) = server.handle_line(r#"{"jsonrpc": "2.0", "method": "shutdown"}"#);
+        assert!(response.is_none());
+        assert!(shutdown);
+    }
+    
+    #[test]
+    fn scan_events_report_batches_and_skipped_files() {
+        let dir = temp_dir();
+        fs::write(dir.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(dir.join("b.rs"), "fn b() {}\n").unwrap();
+        fs::write(dir.join("big.rs"), "// padding\n".repeat(16)).unwrap();
+        let manager = Arc::new(ProjectManager::new());
+        let config = ProjectConfig::builder().max_file_size(64).build();
+        let project = manager.create_project_with_config("events", "", &dir, config).unwrap();
+        let service = AsyncFileService::new(manager);
+        
+        let events: Vec<ScanEvent> = tokio::runtime::Runtime::new().unwrap().block_on(async {
+            use tokio_stream::StreamExt;
+            service.scan_project_events(project.id).collect().await
+        });
+        
+        let indexed = events.iter().filter(|e| matches!(e, ScanEvent::FileIndexed { .. })).count();
+        assert_eq!(indexed, 2);
+        assert!(events.iter().any(|e| matches!(e, ScanEvent::Error { path: Some(_), code: "E_FILE_TOO_LARGE", .. })));
+        assert!(matches!(events[events.len() - 2], ScanEvent::Progress { done: 3, total: 3 }));
+        assert!(matches!(events[events.len() - 1], ScanEvent::Done { indexed: 2, skipped: 1 }));
+        
+        let report = project.scan_files().unwrap();
+        assert_eq!((report.indexed, report.skipped.len()), (2, 1));
+    }
+    
+    #[test]
+    fn detect_language_ranks_shebang_and_keywords_over_conflicting_extension() {
+        let sample = "#!/usr/bin/env ruby\nbegin\n  puts 1 unless done\nrescue\n  retry\nend\n";
+        let candidates = detect_language(Path::new("build.py"), sample);
+        assert_eq!(candidates[0].0, Language::Ruby);
+        assert_eq!(candidates[1], (Language::Python, EXTENSION_CONFIDENCE));
+        assert_eq!(most_likely_language(Path::new("build.py"), sample), Language::Ruby);
+        
+        // 扩展名与 shebang 一致时置信度截断到 1
+        let candidates = detect_language(Path::new("tool.py"), "#!/usr/bin/python3\n");
+        assert_eq!(candidates, vec![(Language::Python, 1.0)]);
+    }
+    
+    #[test]
+    fn detect_language_breaks_ties_by_declaration_order() {
+        let path = Path::new("notes");
+        let candidates = detect_language(path, "const x = function () { return this }");
+        assert!(candidates.len() > 1);
+        for pair in candidates.windows(2) {
+            let (a, a_score) = pair[0];
+            let (b, b_score) = pair[1];
+            let position = |language| Language::ALL.iter().position(|&l| l == language);
+            assert!(a_score > b_score || (a_score == b_score && position(a) < position(b)), "{:?}", candidates);
+        }
+        
+        // 只有关键字证据时达不到阈值
+        assert!(candidates[0].1 < LANGUAGE_CONFIDENCE_THRESHOLD);
+        assert_eq!(most_likely_language(path, "const x = function () { return this }"), Language::Unknown);
+    }
+    
+    #[test]
+    fn memory_file_system_infers_directories() {
+        let file_system = MemoryFileSystem::new();
+        file_system.insert("/p/src/main.rs", "fn main() {}\n");
+        file_system.insert("/p/src/util/mod.rs", "");
+        file_system.insert("/p/README.md", "# p\n");
+        
+        let root = file_system.metadata(Path::new("/p")).unwrap();
+        assert!(root.is_dir && !root.is_file);
+        let file = file_system.metadata(Path::new("/p/src/main.rs")).unwrap();
+        assert!(file.is_file && !file.is_dir);
+        assert_eq!(file.len, 13);
+        assert_eq!(
+            file_system.metadata(Path::new("/p/missing")).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        // 路径前缀相同但不是父目录
+        assert!(file_system.metadata(Path::new("/p/sr")).is_err());
+        
+        assert_eq!(
+            file_system.read_dir(Path::new("/p")).unwrap(),
+            vec![PathBuf::from("/p/README.md"), PathBuf::from("/p/src")]
+        );
+        assert_eq!(
+            file_system.read_dir(Path::new("/p/src")).unwrap(),
+            vec![PathBuf::from("/p/src/main.rs"), PathBuf::from("/p/src/util")]
+        );
+        assert!(file_system.read_dir(Path::new("/q")).is_err());
+        
+        assert!(file_system.remove(Path::new("/p/src/util/mod.rs")));
+        assert!(file_system.metadata(Path::new("/p/src/util")).is_err());
+    }
+    
+    #[test]
+    fn project_scans_memory_file_system() {
+        let file_system = Arc::new(MemoryFileSystem::new());
+        file_system.insert("/p/src/main.rs", "// 入口\nfn main() {}\n");
+        file_system.insert("/p/src/lib.py", "def f():\n    pass\n");
+        file_system.insert("/p/target/debug/out.rs", "fn x() {}\n");
+        file_system.insert("/p/.hidden/a.rs", "fn a() {}\n");
+        file_system.insert("/p/notes.txt", "x\n");
+        
+        let config = ProjectConfig::builder().defer_checksums(true).build();
+        let project = Project::with_file_system("memory", "", "/p", config, file_system.clone()).unwrap();
+        let report = project.scan_files().unwrap();
+        assert_eq!(report.indexed, 2);
+        
+        let main = project.find_file_by_path("/p/src/main.rs").unwrap();
+        assert_eq!(main.language, Language::Rust);
+        assert_eq!(main.lines, 2);
+        assert_eq!(main.line_breakdown.comment, 1);
+        assert!(!main.has_checksum());
+        
+        let mut main = main;
+        main.ensure_checksum_in(&*file_system).unwrap();
+        assert_eq!(main.checksum, FileInfo::from_path_in(&*file_system, "/p/src/main.rs").unwrap().checksum);
+        assert_eq!(project.ensure_checksums(), 2);
+        assert!(project.verify_checksum(&main.id).unwrap());
+        
+        file_system.insert("/p/src/main.rs", "fn main() {}\n");
+        assert!(!project.verify_checksum(&main.id).unwrap());
+        
+        assert!(matches!(
+            Project::with_file_system("missing", "", "/q", ProjectConfig::default(), file_system),
+            Err(ChangoEditorError::PathNotFound(_))
+        ));
+    }
+    
+    #[test]
+    fn large_scan_always_delivers_rescanned() {
+        let dir = temp_dir();
+        for i in 0..PROJECT_EVENT_CAPACITY + 10 {
+            fs::write(dir.join(format!("f{}.rs", i)), "fn f() {}\n").unwrap();
+        }
+        
+        let project = Project::new("events", "", &dir).unwrap();
+        let events = project.subscribe();
+        project.scan_files().unwrap();
+        assert_eq!(events.try_iter().collect::<Vec<_>>(), vec![ProjectEvent::Rescanned]);
+        
+        for i in 0..PROJECT_EVENT_CAPACITY {
+            fs::remove_file(dir.join(format!("f{}.rs", i))).unwrap();
+        }
+        project.rescan_files().unwrap();
+        assert_eq!(events.try_iter().last(), Some(ProjectEvent::Rescanned));
+    }
+    
+    #[test]
+    fn saved_file_keeps_disk_mtime_and_is_not_rescanned() {
+        let dir = temp_dir();
+        let path = dir.join("a.rs");
+        fs::write(&path, "fn a() {}\n").unwrap();
+        let project = Project::new("saved", "", &dir).unwrap();
+        project.scan_files().unwrap();
+        let id = project.file_id(&path);
+        
+        let content = b"fn a() {}\nfn b() {}\n";
+        fs::write(&path, content).unwrap();
+        set_mtime(&path, 2_000_000);
+        project.mark_file_saved(&id, content).unwrap();
+        
+        let saved = project.get_file(&id).unwrap();
+        assert_eq!(saved.modified_at, UNIX_EPOCH + Duration::from_secs(2_000_000));
+        assert_eq!(saved.lines, 2);
+        assert!(project.rescan_files().unwrap().is_empty());
+    }
+    
+    #[test]
+    fn file_ids_are_stable_across_rescans() {
+        let dir = temp_dir();
+        let path = dir.join("a.rs");
+        fs::write(&path, "fn a() {}\n").unwrap();
+        
+        let project = Project::new("stable", "", &dir).unwrap();
+        let id = project.file_id(&path);
+        assert_eq!(id, project.file_id(&dir.join(".").join("a.rs")));
+        assert_ne!(id, Project::new("other", "", &dir).unwrap().file_id(&path));
+        
+        project.add_file(FileInfo::from_path(&path).unwrap()).unwrap();
+        fs::write(&path, "fn a() {}\nfn b() {}\n").unwrap();
+        project.add_file(FileInfo::from_path(&path).unwrap()).unwrap();
+        
+        let files = project.get_all_files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].id, id);
+        assert_eq!(files[0].lines, 2);
+        
+        // 项目重新创建 (如从状态文件恢复) 后 ID 不变
+        let mut reopened = Project::new("stable", "", &dir).unwrap();
+        reopened.id = project.id;
+        reopened.scan_files().unwrap();
+        assert_eq!(reopened.find_file_by_path(&path).unwrap().id, id);
+        
+        // 重命名后在原路径新建文件，不能覆盖已重命名的文件
+        project.rename_file(&id, dir.join("b.rs")).unwrap();
+        fs::write(&path, "fn c() {}\n").unwrap();
+        project.add_file(FileInfo::from_path(&path).unwrap()).unwrap();
+        assert_eq!(project.get_all_files().len(), 2);
+        assert_eq!(project.get_file(&id).unwrap().path, dir.join("b.rs"));
+    }
+    
+    #[test]
+    fn partial_scan_keeps_finished_batches_when_cancelled() {
+        let dir = temp_dir();
+        for i in 0..SCAN_BATCH_SIZE + 10 {
+            fs::write(dir.join(format!("f{}.rs", i)), "fn f() {}\n").unwrap();
+        }
+        let project = Project::new("partial", "", &dir).unwrap();
+        
+        // 第一批写入索引后触发取消
+        let scan = project.scan_files_partial(&|| {
+            project.files.read().unwrap_or_else(|e| e.into_inner()).len() >= SCAN_BATCH_SIZE
+        }).unwrap();
+        assert!(!scan.completed);
+        assert_eq!(scan.files.len(), SCAN_BATCH_SIZE);
+        assert_eq!(project.get_all_files().len(), SCAN_BATCH_SIZE);
+    }
+    
+    #[test]
+    fn async_scan_with_zero_deadline_is_incomplete() {
+        let dir = temp_dir();
+        fs::write(dir.join("a.rs"), "fn a() {}\n").unwrap();
+        let manager = Arc::new(ProjectManager::new());
+        let project = manager.create_project("deadline", "", &dir).unwrap();
+        let service = AsyncFileService::new(manager);
+        
+        let scan = tokio::runtime::Runtime::new().unwrap()
+            .block_on(service.scan_project_async_timeout(project.id, Duration::ZERO))
+            .unwrap();
+        assert!(!scan.completed);
+        assert!(scan.files.is_empty());
+        assert!(project.get_all_files().is_empty());
+    }
+    
+    #[test]
+    fn diff_lines_rewrites_large_files_in_linear_space() {
+        // 全部行都改变时编辑距离为 N+M，保存每一轮前沿的实现需要 O((N+M)²) 内存
+        let old: Vec<String> = (0..3000).map(|i| format!("old {}\n", i)).collect();
+        let new: Vec<String> = (0..3000).map(|i| format!("new {}\n", i)).collect();
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let new: Vec<&str> = new.iter().map(String::as_str).collect();
+        
+        let ops = diff_lines(&old, &new);
+        assert_eq!(ops.len(), 6000);
+        assert!(ops[..3000].iter().enumerate().all(|(i, op)| *op == DiffOp::Delete(i)));
+        assert!(ops[3000..].iter().enumerate().all(|(i, op)| *op == DiffOp::Insert(i)));
+    }
+    
+    #[test]
+    fn diff_lines_matches_longest_common_subsequence() {
+        // 简单的线性同余生成器，保证用例可复现
+        let mut seed = 0x2545_f491_u32;
+        let mut next = |limit: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) % limit
+        };
+        let words = ["a", "b", "c", "d"];
+        
+        for _ in 0..200 {
+            let old: Vec<&str> = (0..next(12)).map(|_| words[next(4) as usize]).collect();
+            let new: Vec<&str> = (0..next(12)).map(|_| words[next(4) as usize]).collect();
+            
+            let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+            for i in (0..old.len()).rev() {
+                for j in (0..new.len()).rev() {
+                    lcs[i][j] = if old[i] == new[j] {
+                        lcs[i + 1][j + 1] + 1
+                    } else {
+                        lcs[i + 1][j].max(lcs[i][j + 1])
+                    };
+                }
+            }
+            
+            let ops = diff_lines(&old, &new);
+            let (mut x, mut y, mut equal) = (0, 0, 0);
+            for op in &ops {
+                match *op {
+                    DiffOp::Equal(a, b) => {
+                        assert_eq!((a, b), (x, y));
+                        assert_eq!(old[a], new[b]);
+                        x += 1;
+                        y += 1;
+                        equal += 1;
+                    }
+                    DiffOp::Delete(a) => {
+                        assert_eq!(a, x);
+                        x += 1;
+                    }
+                    DiffOp::Insert(b) => {
+                        assert_eq!(b, y);
+                        y += 1;
+                    }
+                }
+            }
+            assert_eq!((x, y), (old.len(), new.len()));
+            assert_eq!(equal, lcs[0][0], "{:?} -> {:?}", old, new);
+        }
+    }
+    
+    #[test]
+    fn scan_cache_tolerates_old_and_broken_files() {
+        let dir = temp_dir();
+        let path = dir.join("a.rs");
+        fs::write(&path, "fn a() {}\n").unwrap();
+        
+        let project = Project::new("cache", "", &dir).unwrap();
+        let mut cache = ScanCache::new();
+        project.rescan_with_cache(&mut cache).unwrap();
+        
+        // 早期版本的条目没有后来加入的字段
+        let mut json: serde_json::Value = serde_json::to_value(&cache).unwrap();
+        for entry in json["entries"].as_object_mut().unwrap().values_mut() {
+            let entry = entry.as_object_mut().unwrap();
+            entry.remove("truncated");
+            entry.remove("is_generated");
+        }
+        let cache_path = temp_file(".json", json.to_string().as_bytes());
+        let mut loaded = ScanCache::load_from(&cache_path).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert!(project.rescan_with_cache(&mut loaded).unwrap().is_empty());
+        
+        fs::write(&cache_path, "{ not json").unwrap();
+        assert!(ScanCache::load_from(&cache_path).unwrap().is_empty());
+        assert!(ScanCache::load_from(dir.join("missing.json")).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn memory_projects_write_through_their_file_system() {
+        let file_system = Arc::new(MemoryFileSystem::new());
+        file_system.insert("/p/a.rs", "fn a() {}\r\nfn b() {}\r\n");
+        file_system.insert("/p/b.bin", vec![0u8, 1, 2, 3]);
+        let project = Arc::new(
+            Project::with_file_system("memory", "", "/p", ProjectConfig::default(), file_system.clone()).unwrap()
+        );
+        project.scan_files().unwrap();
+        let id = project.find_file_by_path("/p/a.rs").unwrap().id;
+        
+        project.normalize_line_endings(&id, LineEnding::Lf).unwrap();
+        let mut content = String::new();
+        file_system.open(Path::new("/p/a.rs")).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "fn a() {}\nfn b() {}\n");
+        assert_eq!(project.get_file(&id).unwrap().line_ending, LineEnding::Lf);
+        
+        assert!(is_probably_binary_in(&*file_system, "/p/b.bin"));
+        assert!(!is_probably_binary_in(&*file_system, "/p/a.rs"));
+        assert!(!is_probably_binary("/p/b.bin"));
+        
+        assert!(matches!(
+            ProjectWatcher::new(project, Duration::from_millis(10)),
+            Err(ChangoEditorError::ValidationError(_))
+        ));
+    }
+    
+    #[test]
+    fn removing_projects_while_listing_recent_does_not_deadlock() {
+        let dir = temp_dir();
+        let path = dir.to_path_buf();
+        let manager = Arc::new(ProjectManager::new());
+        let stop = Arc::new(AtomicBool::new(false));
+        let (done_tx, done_rx) = crossbeam_channel::unbounded();
+        
+        for _ in 0..4 {
+            let manager = manager.clone();
+            let stop = stop.clone();
+            let done_tx = done_tx.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    manager.get_recent_projects(10);
+                }
+                done_tx.send(()).unwrap();
+            });
+        }
+        {
+            let manager = manager.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    let project = manager.create_project(format!("p{}", i), String::new(), &path).unwrap();
+                    manager.remove_project(&project.id).unwrap();
+                }
+                stop.store(true, Ordering::Relaxed);
+                done_tx.send(()).unwrap();
+            });
+        }
+        
+        for _ in 0..5 {
+            assert!(done_rx.recv_timeout(Duration::from_secs(30)).is_ok(), "加锁顺序不一致导致死锁");
+        }
+        assert!(manager.get_recent_projects(10).is_empty());
+    }
+    
+    #[test]
+    fn search_contents_matches_lines_across_files() {
+        let dir = temp_dir();
+        fs::write(dir.join("a.rs"), "fn main() {\r\n    println!(\"Hello\");\r\n}\r\n").unwrap();
+        fs::write(dir.join("b.py"), "# hello world\nprint(42)\n").unwrap();
+        fs::write(dir.join("wide.rs"), utf16le("first\r\nsay HELLO\r\n")).unwrap();
+        
+        let project = Project::new("search", "", &dir).unwrap();
+        project.scan_files().unwrap();
+        let a = project.file_id(&dir.join("a.rs"));
+        let b = project.file_id(&dir.join("b.py"));
+        let wide = project.file_id(&dir.join("wide.rs"));
+        let found = |matches: Vec<ContentMatch>| {
+            let mut found: Vec<(Uuid, usize, String)> = matches.into_iter()
+                .map(|m| (m.file_id, m.line_number, m.line))
+                .collect();
+            found.sort();
+            found
+        };
+        
+        let mut expected = vec![(a, 2, "    println!(\"Hello\");".to_string())];
+        assert_eq!(found(project.search_contents("Hello", true).unwrap()), expected);
+        
+        // 不区分大小写时也匹配 UTF-16 文件中解码后的行
+        expected.push((b, 1, "# hello world".to_string()));
+        expected.push((wide, 2, "say HELLO".to_string()));
+        expected.sort();
+        assert_eq!(found(project.search_contents("hello", false).unwrap()), expected);
+        
+        let regex = regex::Regex::new(r"^print\(\d+\)$").unwrap();
+        assert_eq!(found(project.search_contents_regex(regex).unwrap()), vec![(b, 2, "print(42)".to_string())]);
+        
+        // 索引后增长到超过上限的文件被跳过
+        let config = ProjectConfig::builder().max_file_size(64).build();
+        let limited = Project::with_config("limited", "", &dir, config).unwrap();
+        limited.scan_files().unwrap();
+        fs::write(dir.join("b.py"), format!("# hello world\n{}", "x".repeat(100))).unwrap();
+        let results = limited.search_contents("hello", false).unwrap();
+        assert!(results.iter().all(|m| m.file_id != limited.file_id(&dir.join("b.py"))), "{:?}", results);
+        assert!(results.iter().any(|m| m.line == "say HELLO"));
+    }
+    
+    #[test]
+    fn preview_counts_decoded_lines_through_any_file_system() {
+        let file_system = MemoryFileSystem::new();
+        let text: String = (1..=6).map(|i| format!("// {}\r\n", i)).collect();
+        file_system.insert("/p/wide.rs", utf16le(&text));
+        file_system.insert("/p/plain.rs", text.clone());
+        
+        let (preview, decoded) = FileInfo::preview_from_path_in(&file_system, "/p/wide.rs", 1024).unwrap();
+        assert_eq!(decoded.trim_start_matches('\u{feff}'), text);
+        assert_eq!(preview.encoding, "utf-16le");
+        assert_eq!((preview.lines, preview.line_ending), (6, LineEnding::CrLf));
+        assert!(!preview.truncated);
+        
+        // 截断时只统计已读取部分的行
+        let (preview, decoded) = FileInfo::preview_from_path_in(&file_system, "/p/wide.rs", 2 + 12 * 2).unwrap();
+        assert_eq!(decoded.trim_start_matches('\u{feff}'), "// 1\r\n// 2\r\n");
+        assert_eq!(preview.lines, 2);
+        assert!(preview.truncated);
+        assert_eq!(preview.size, file_system.metadata(Path::new("/p/wide.rs")).unwrap().len);
+        
+        let (preview, _) = FileInfo::preview_from_path_in(&file_system, "/p/plain.rs", 1024).unwrap();
+        assert_eq!(preview.lines, 6);
+        assert_eq!(preview.language, Language::Rust);
+    }
+    
+    #[test]
+    fn hidden_entries_are_skipped_unless_included() {
+        let dir = temp_dir();
+        for file in [".git/hooks/pre-commit.rs", ".github/scripts/ci.rs", ".github/.cache/x.rs", "src/main.rs"] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "fn f() {}\n").unwrap();
+        }
+        let indexed = |config: ProjectConfig| {
+            let project = Project::with_config("hidden", "", &dir, config).unwrap();
+            project.scan_files().unwrap();
+            let mut paths: Vec<String> = project.get_all_files().iter()
+                .map(|f| f.path.strip_prefix(&*dir).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect();
+            paths.sort();
+            paths
+        };
+        
+        for respect_gitignore in [true, false] {
+            let base = || ProjectConfig::builder().respect_gitignore(respect_gitignore);
+            assert_eq!(indexed(base().build()), ["src/main.rs"]);
+            // 即使没有排除模式，.git 也作为隐藏目录被跳过
+            assert_eq!(
+                indexed(base().exclude_patterns(Vec::<String>::new()).hidden_include_pattern(".github/**").build()),
+                [".github/.cache/x.rs", ".github/scripts/ci.rs", "src/main.rs"]
+            );
+            assert_eq!(
+                indexed(base().exclude_patterns(Vec::<String>::new()).include_hidden(true).build()),
+                [".git/hooks/pre-commit.rs", ".github/.cache/x.rs", ".github/scripts/ci.rs", "src/main.rs"]
+            );
+        }
+        
+        assert!(ProjectConfig::builder().hidden_include_pattern(".github/**[").build().validate().is_err());
+    }
+    
+    #[test]
+    fn line_shingles_ignore_whitespace_and_blank_lines() {
+        assert!(line_shingles("").is_empty());
+        assert!(line_shingles("\n  \n\t\n").is_empty());
+        assert_eq!(line_shingles("a\nb").len(), 1);
+        assert_eq!(line_shingles("a\nb\nc\nd\n").len(), 2);
+        assert_eq!(line_shingles("a\nb\nc\nd\n"), line_shingles("  a\n\nb \n\tc\r\n\n d"));
+        assert_eq!(line_shingles("let  x =\t1;\ny\nz"), line_shingles("let x = 1;\ny\nz"));
+        assert_ne!(line_shingles("a\nb\nc"), line_shingles("c\nb\na"));
+    }
+    
+    #[test]
+    fn find_similar_compares_files_of_the_same_language() {
+        let dir = temp_dir();
+        let body: String = (0..20).map(|i| format!("    let v{} = {};\n", i, i)).collect();
+        let write = |name: &str, content: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+        let original = write("a.rs", format!("fn a() {{\n{}}}\n", body).as_bytes());
+        let copy = write("b.rs", format!("fn b() {{\n{}}}\n", body).as_bytes());
+        let wide = write("c.rs", &utf16le(&format!("fn c() {{\r\n{}}}\r\n", body.replace('\n', "\r\n"))));
+        write("d.rs", b"fn d() {}\n");
+        // 内容相同但语言不同
+        write("e.py", format!("def e():\n{}", body).as_bytes());
+        write("f.py", format!("def f():\n{}", body).as_bytes());
+        
+        let project = Project::new("similar", "", &dir).unwrap();
+        project.scan_files().unwrap();
+        let id = |path: &Path| project.file_id(path);
+        let pair = |a: Uuid, b: Uuid| if a < b { (a, b) } else { (b, a) };
+        
+        let pairs = project.find_similar(0.8);
+        let found: HashSet<(Uuid, Uuid)> = pairs.iter().map(|&(a, b, _)| pair(a, b)).collect();
+        assert_eq!(found.len(), 4, "{:?}", pairs);
+        assert!(found.contains(&pair(id(&original), id(&copy))));
+        assert!(found.contains(&pair(id(&original), id(&wide))));
+        assert!(pairs.iter().all(|&(_, _, similarity)| (0.8..=1.0).contains(&similarity)));
+        assert!(pairs.windows(2).all(|w| w[0].2 >= w[1].2));
+        for (a, b, _) in &pairs {
+            let language = |id| project.get_file(id).unwrap().language;
+            assert_eq!(language(a), language(b));
+        }
+        
+        // 阈值为 0 时差异很大的文件也会列出，阈值超过 1 时没有结果
+        assert!(project.find_similar(0.0).len() > pairs.len());
+        assert!(project.find_similar(1.01).is_empty());
+        
+        // 每种语言只比较按路径排序的前两个文件: a.rs 与 b.rs，e.py 与 f.py
+        let limited: HashSet<(Uuid, Uuid)> = project.find_similar_with_limit(0.0, 2).iter()
+            .map(|&(a, b, _)| pair(a, b))
+            .collect();
+        assert_eq!(limited, HashSet::from([
+            pair(id(&original), id(&copy)),
+            pair(id(&dir.join("e.py")), id(&dir.join("f.py"))),
+        ]));
+    }
+    
+    struct PanickyLinter;
+    
+    impl DiagnosticProcessor for PanickyLinter {
+        fn diagnose(&self, file: &FileInfo) -> Result<Vec<Diagnostic>> {
+            if file.name.starts_with("panic") {
+                panic!("诊断失败");
+            }
+            Ok(vec![Diagnostic { line: file.lines, column: 1, severity: Severity::Info, message: file.name.clone() }])
+        }
+        
+        fn get_name(&self) -> &str {
+            "panicky"
+        }
+    }
+    
+    #[test]
+    fn diagnostic_runner_isolates_panics_and_reuses_its_pool() {
+        let dir = temp_dir();
+        let files: Vec<FileInfo> = ["a.rs", "b.rs", "panic.rs", "c.rs"].iter()
+            .map(|name| {
+                let path = dir.join(name);
+                fs::write(&path, "fn f() {}\n").unwrap();
+                FileInfo::from_path(&path).unwrap()
+            })
+            .collect();
+        
+        let runner = ConcurrentDiagnosticRunner::new(PanickyLinter, 2);
+        for _ in 0..2 {
+            let results = runner.run(files.clone()).unwrap();
+            assert_eq!(results.len(), files.len());
+            for file in &files {
+                match &results[&file.id] {
+                    Ok(diagnostics) => assert_eq!(diagnostics[0].message, file.name),
+                    Err(e) => {
+                        assert_eq!(file.name, "panic.rs");
+                        assert!(matches!(e, ChangoEditorError::ProcessorPanic(message) if message.contains("诊断失败")));
+                    }
+                }
+            }
+        }
+        assert!(runner.run(Vec::new()).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn fuzzy_score_rewards_contiguous_and_boundary_matches() {
+        // 每个字符 1 分，相邻再加 5 分，开头或分隔符之后再加 3 分
+        assert_eq!(fuzzy_score("abc", "ab"), Some(4 + 6));
+        assert_eq!(fuzzy_score("axbc", "ab"), Some(4 + 1));
+        assert_eq!(fuzzy_score("x_b", "b"), Some(1 + 3));
+        assert_eq!(fuzzy_score("xb", "b"), Some(1));
+        assert_eq!(fuzzy_score("main.rs", "mainrs"), Some(4 + 6 * 3 + 4 + 6));
+        assert_eq!(fuzzy_score("abc", "a b"), fuzzy_score("abc", "ab"));
+        assert_eq!(fuzzy_score("ab", "ba"), None);
+        assert_eq!(fuzzy_score("ab", "abc"), None);
+        assert_eq!(fuzzy_score("ab", ""), Some(0));
+    }
+    
+    #[test]
+    fn search_modes_rank_and_filter_files() {
+        let file_system = Arc::new(MemoryFileSystem::new());
+        for path in ["/p/src/main.rs", "/p/src/remain.rs", "/p/src/domain/util.rs", "/p/src/notes.rs"] {
+            file_system.insert(path, "fn f() {}\n");
+        }
+        let project = Project::with_file_system("search", "", "/p", ProjectConfig::default(), file_system).unwrap();
+        project.scan_files().unwrap();
+        let names = |query: &str, mode: SearchMode| -> Vec<String> {
+            project.search_files_with(query, mode).into_iter().map(|file| file.name).collect()
+        };
+        
+        // 文件名命中 (加 FUZZY_NAME_BONUS) 排在仅路径命中之前，得分高者在前
+        assert_eq!(names("MAIN", SearchMode::Fuzzy), ["main.rs", "remain.rs", "util.rs"]);
+        assert_eq!(names("dutil", SearchMode::Fuzzy), ["util.rs"]);
+        
+        let mut all_terms = names("src main", SearchMode::AllTerms);
+        all_terms.sort();
+        assert_eq!(all_terms, ["main.rs", "remain.rs", "util.rs"]);
+        assert_eq!(names("domain util", SearchMode::AllTerms), ["util.rs"]);
+        assert!(names("util notes", SearchMode::AllTerms).is_empty());
+        assert!(names("src main", SearchMode::Substring).is_empty());
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn following_symlinks_indexes_each_real_file_once() {
+        let dir = temp_dir();
+        fs::create_dir(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "fn f() {}\n").unwrap();
+        // 按字典序排在直接路径之前的别名，以及指回项目根目录的循环链接
+        std::os::unix::fs::symlink(dir.join("src/lib.rs"), dir.join("alias.rs")).unwrap();
+        std::os::unix::fs::symlink(&*dir, dir.join("src/loop")).unwrap();
+        
+        let mut paths = vec![dir.join("alias.rs"), dir.join("src/lib.rs"), dir.join("missing.rs")];
+        dedup_real_paths(&dir, &mut paths);
+        assert_eq!(paths, [dir.join("src/lib.rs"), dir.join("missing.rs")]);
+        
+        for respect_gitignore in [true, false] {
+            let config = ProjectConfig::builder()
+                .follow_symlinks(true)
+                .respect_gitignore(respect_gitignore)
+                .build();
+            let project = Project::with_config("links", "", &dir, config).unwrap();
+            project.scan_files().unwrap();
+            let files = project.get_all_files();
+            assert_eq!(files.len(), 1, "{:?}", files.iter().map(|f| &f.path).collect::<Vec<_>>());
+            assert_eq!(files[0].path, dir.join("src/lib.rs"));
+        }
+    }
+    
+    #[test]
+    fn file_diff_reports_size_line_checksum_and_mtime_changes() {
+        let path = temp_file(".rs", b"fn a() {}\nfn b() {}\n");
+        set_mtime(&path, 1_000);
+        let old = FileInfo::from_path(&path).unwrap();
+        assert!(old.diff(&old).is_empty());
+        
+        // 内容不变，只有修改时间变化
+        set_mtime(&path, 2_000);
+        let touched = FileInfo::from_path(&path).unwrap();
+        let diff = old.diff(&touched);
+        assert_eq!((diff.size_delta, diff.line_delta, diff.checksum_changed), (0, 0, Some(false)));
+        assert_eq!(diff.modified_at, Some((UNIX_EPOCH + Duration::from_secs(1_000), UNIX_EPOCH + Duration::from_secs(2_000))));
+        assert!(!diff.is_empty());
+        assert!(!diff.content_changed());
+        
+        // 大小与行数不变，但内容不同
+        fs::write(&path, "fn x() {}\nfn y() {}\n").unwrap();
+        set_mtime(&path, 1_000);
+        let rewritten = FileInfo::from_path(&path).unwrap();
+        let diff = old.diff(&rewritten);
+        assert_eq!((diff.size_delta, diff.line_delta, diff.checksum_changed), (0, 0, Some(true)));
+        assert_eq!(diff.modified_at, None);
+        assert!(diff.content_changed());
+        
+        fs::write(&path, "fn a() {}\n").unwrap();
+        let shrunk = FileInfo::from_path(&path).unwrap();
+        let diff = old.diff(&shrunk);
+        assert_eq!((diff.size_delta, diff.line_delta), (-10, -1));
+        assert_eq!(shrunk.diff(&old).size_delta, 10);
+        
+        // 没有校验和时无法比较内容，按大小与行数判断
+        let fast = FileInfo::from_path_fast(&path).unwrap();
+        assert_eq!(old.diff(&fast).checksum_changed, None);
+        assert!(old.diff(&fast).content_changed());
+        assert!(!shrunk.diff(&fast).content_changed());
+    }
+    
+    #[test]
+    fn shebang_detection_handles_env_and_versioned_interpreters() {
+        assert_eq!(Language::from_content("#!/usr/bin/python3.11"), Language::Python);
+        assert_eq!(Language::from_content("#!/usr/bin/env python3"), Language::Python);
+        assert_eq!(Language::from_content("#!/usr/bin/env -S PYTHONPATH=. python3.11 -u"), Language::Python);
+        assert_eq!(Language::from_content("\u{feff}#! /usr/local/bin/node"), Language::JavaScript);
+        assert_eq!(Language::from_content("#!/usr/bin/env"), Language::Unknown);
+        assert_eq!(Language::from_content("#!/bin/sh"), Language::Unknown);
+        assert_eq!(Language::from_content("python3"), Language::Unknown);
+        
+        // 扩展名优先，无扩展名时回退到 shebang
+        assert_eq!(Language::detect(Path::new("tool.rb"), "#!/usr/bin/env python3"), Language::Ruby);
+        assert_eq!(Language::detect(Path::new("tool"), "#!/usr/bin/env python3"), Language::Python);
+        
+        let dir = temp_dir();
+        fs::write(dir.join("manage"), "#!/usr/bin/env python3.11\nimport sys\n").unwrap();
+        fs::write(dir.join("run"), "#!/bin/sh\necho hi\n").unwrap();
+        assert_eq!(FileInfo::from_path(dir.join("manage")).unwrap().language, Language::Python);
+        
+        // 无扩展名的脚本只有 shebang 能识别语言时才被索引
+        let project = Project::new("scripts", "", &dir).unwrap();
+        project.scan_files().unwrap();
+        let files = project.get_all_files();
+        assert_eq!(files.len(), 1);
+        assert_eq!((files[0].name.as_str(), files[0].language), ("manage", Language::Python));
+    }
+    
+    #[test]
+    fn exclude_patterns_prune_whole_directories() {
+        let set = ProjectConfig::builder().exclude_patterns(["target/*", "/build/**"]).build().exclude_set().unwrap();
+        let root = Path::new("/p");
+        for excluded in ["target", "target/debug", "target/debug/deep/x.rs", "crates/a/target/out.rs", "build", "build/gen/x.rs"] {
+            assert!(is_excluded(&set, root, &root.join(excluded)), "{}", excluded);
+        }
+        for kept in ["src/target.rs", "targets/x.rs", "crates/a/build/x.rs"] {
+            assert!(!is_excluded(&set, root, &root.join(kept)), "{}", kept);
+        }
+        assert!(!is_excluded(&set, root, root));
+        
+        let dir = temp_dir();
+        for file in ["src/main.rs", "target/debug/build/out.rs", "target/a.rs", "crates/a/target/b.rs", "crates/a/src/lib.rs"] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "fn f() {}\n").unwrap();
+        }
+        for respect_gitignore in [true, false] {
+            let config = ProjectConfig::builder().respect_gitignore(respect_gitignore).build();
+            let project = Project::with_config("excluded", "", &dir, config).unwrap();
+            let report = project.scan_files().unwrap();
+            assert_eq!(report.indexed, 2);
+            assert!(project.get_all_files().iter().all(|f| !f.path.components().any(|c| c.as_os_str() == "target")));
+        }
+    }
+    
+    #[test]
+    fn validate_lists_every_invalid_pattern() {
+        let config = ProjectConfig::builder()
+            .exclude_patterns(["target/**[", "node_modules/*", "{src,lib"])
+            .build();
+        let message = match config.validate() {
+            Err(ChangoEditorError::ValidationError(message)) => message,
+            other => panic!("应拒绝无效模式: {:?}", other),
+        };
+        assert!(message.contains("target/**["), "{}", message);
+        assert!(message.contains("{src,lib"), "{}", message);
+        assert!(!message.contains("node_modules"), "{}", message);
+        
+        let dir = temp_dir();
+        assert!(matches!(
+            Project::with_config("invalid", "", &dir, config),
+            Err(ChangoEditorError::ValidationError(_))
+        ));
+        
+        let generated = ProjectConfig::builder().generated_pattern("*.pb.[").build();
+        assert!(matches!(generated.validate(), Err(ChangoEditorError::ValidationError(m)) if m.contains("*.pb.[")));
+        assert!(ProjectConfig::default().validate().is_ok());
+    }
+    
+    #[test]
+    fn generated_files_are_indexed_but_left_out_of_statistics() {
+        let dir = temp_dir();
+        for (file, content) in [
+            ("src/main.rs", "fn main() {}\n"),
+            ("api/api.pb.go", "package api\n\nfunc F() {}\n"),
+            ("web/app.min.js", "var a=1;\n"),
+            ("gen/schema.rs", "struct S;\n"),
+        ] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let scan = |config: ProjectConfig| {
+            let project = Project::with_config("generated", "", &dir, config).unwrap();
+            project.scan_files().unwrap();
+            project
+        };
+        
+        let project = scan(ProjectConfig::builder().generated_pattern("/gen/**").build());
+        assert_eq!(project.get_all_files().len(), 4);
+        let generated = |file: &str| project.find_file_by_path(dir.join(file)).unwrap().is_generated;
+        assert!(generated("api/api.pb.go") && generated("web/app.min.js") && generated("gen/schema.rs"));
+        assert!(!generated("src/main.rs"));
+        
+        let stats = project.get_statistics();
+        assert_eq!((stats.total_files, stats.total_lines), (1, 1));
+        assert_eq!(stats.language_stats.keys().collect::<Vec<_>>(), [&Language::Rust]);
+        assert_eq!(stats.language_stats[&Language::Rust].file_count, 1);
+        
+        let stats = scan(ProjectConfig::builder()
+            .generated_pattern("/gen/**")
+            .include_generated_in_statistics(true)
+            .build())
+            .get_statistics();
+        assert_eq!((stats.total_files, stats.total_lines), (4, 6));
+        assert_eq!(stats.language_stats[&Language::Rust].file_count, 2);
+        assert_eq!(stats.language_stats[&Language::Go].file_count, 1);
+    }
 }